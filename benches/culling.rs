@@ -0,0 +1,129 @@
+//! Frame time with many stroke groups on the board and only a few of them in view.
+//!
+//! Culling, level of detail and rendering only do work for visible or changed groups, so the
+//! frame time should stay flat as the board grows.
+use bevy::{prelude::*, transform::TransformSystem};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+
+use rnote::{
+    camera::Global2DCamera,
+    tools::brush::StrokeStyle,
+    unit::{
+        cull::{cull_units_system, VisibleUnits},
+        index::{update_unit_index_system, UnitIndex},
+        stroke::{
+            invalidate_strokes_system,
+            lod::{lod_system, LodSettings},
+            material::{HighlighterMaterial, StrokeMaterials},
+            render_strokes_system, stroke_group_bundle, PointMeasurement, PressureSettings, Stroke,
+            StrokeGroup,
+        },
+    },
+};
+
+/// Distance between two groups on the grid, in world units.
+const SPACING: f32 = 100.0;
+/// Half the side of the view, about a hundred groups are in it.
+const VIEW: f32 = 500.0;
+
+fn app(groups: usize) -> App {
+    let mut app = App::new();
+    app.add_plugins((
+        MinimalPlugins,
+        AssetPlugin::default(),
+        TransformPlugin,
+        HierarchyPlugin,
+    ))
+    .init_asset::<Mesh>()
+    .init_asset::<Image>()
+    .init_asset::<ColorMaterial>()
+    .init_asset::<HighlighterMaterial>()
+    .init_resource::<StrokeMaterials>()
+    .init_resource::<PressureSettings>()
+    .init_resource::<LodSettings>()
+    .init_resource::<UnitIndex>()
+    .init_resource::<VisibleUnits>()
+    .add_systems(
+        Update,
+        (
+            cull_units_system,
+            lod_system,
+            invalidate_strokes_system,
+            render_strokes_system,
+        )
+            .chain(),
+    )
+    .add_systems(
+        PostUpdate,
+        update_unit_index_system.after(TransformSystem::TransformPropagate),
+    );
+    app.world.spawn((
+        TransformBundle::default(),
+        OrthographicProjection {
+            area: Rect::new(-VIEW, -VIEW, VIEW, VIEW),
+            ..default()
+        },
+        Global2DCamera,
+    ));
+    let pressure = PressureSettings::default();
+    let columns = (groups as f32).sqrt().ceil() as usize;
+    for i in 0..groups {
+        let mut stroke_group = StrokeGroup::new();
+        stroke_group.strokes.push(Stroke {
+            measurements: (0..8)
+                .map(|j| PointMeasurement::new_point(Vec2::new(j as f32 * 5.0, (j % 2) as f32)))
+                .collect(),
+            ..Stroke::new(StrokeStyle::default())
+        });
+        let position = Vec2::new((i % columns) as f32, (i / columns) as f32) * SPACING;
+        let transform = Transform::from_translation(position.extend(1.0));
+        app.world
+            .spawn(stroke_group_bundle(stroke_group, transform, &pressure));
+    }
+    // index, cull and mesh everything once
+    for _ in 0..3 {
+        app.update();
+    }
+    app
+}
+
+fn move_camera(app: &mut App, position: Vec2) {
+    let mut q_camera = app
+        .world
+        .query_filtered::<&mut Transform, With<Global2DCamera>>();
+    q_camera.single_mut(&mut app.world).translation = position.extend(0.0);
+}
+
+fn frame_time(c: &mut Criterion) {
+    let mut group = c.benchmark_group("frame");
+    group.sample_size(20);
+    for groups in [1_000, 10_000, 100_000] {
+        let mut app = app(groups);
+        group.bench_function(BenchmarkId::new("still", groups), |b| {
+            b.iter(|| app.update())
+        });
+        // pan over the first rows, groups keep entering and leaving the view
+        let mut frame = 0;
+        group.bench_function(BenchmarkId::new("pan", groups), |b| {
+            b.iter(|| {
+                frame += 1;
+                let x = (frame % 200) as f32 * 10.0;
+                move_camera(&mut app, Vec2::new(x, VIEW));
+                app.update()
+            })
+        });
+        // zooming out crosses a level of detail, the visible groups are meshed again
+        group.bench_function(BenchmarkId::new("zoom", groups), |b| {
+            b.iter(|| {
+                let mut q_projection = app.world.query::<&mut OrthographicProjection>();
+                let mut projection = q_projection.single_mut(&mut app.world);
+                projection.scale = if projection.scale > 1.0 { 1.0 } else { 2.0 };
+                app.update()
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, frame_time);
+criterion_main!(benches);
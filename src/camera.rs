@@ -1,4 +1,3 @@
-use bevy::input::common_conditions::*;
 use bevy::{input::mouse::MouseWheel, prelude::*};
 
 use crate::tools::{Tool, ToolBox};
@@ -31,16 +30,15 @@ pub fn setup(mut commands: Commands) {
     ));
 }
 
+/// The middle button pans with the picker, left drags are for selecting and moving units.
+/// Without a tool both buttons pan.
 fn camera_control_condition(
     tool_box: Res<ToolBox>,
     kbd_input: Res<ButtonInput<MouseButton>>,
 ) -> bool {
-    if !kbd_input.any_pressed([MouseButton::Middle, MouseButton::Left]) {
-        return false;
-    }
     match tool_box.current_tool() {
-        Some(Tool::Picker(p)) => !p.picked(),
-        None => true,
+        Some(Tool::Picker(_)) => kbd_input.pressed(MouseButton::Middle),
+        None => kbd_input.any_pressed([MouseButton::Middle, MouseButton::Left]),
         _ => false,
     }
 }
@@ -0,0 +1,8 @@
+#![allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub mod board;
+pub mod camera;
+pub mod debug;
+pub mod mouse;
+pub mod time;
+pub mod tools;
+pub mod unit;
@@ -1,15 +1,7 @@
-#![allow(clippy::too_many_arguments)]
 use bevy::diagnostic::FrameTimeDiagnosticsPlugin;
 use bevy::prelude::*;
 
-mod board;
-mod camera;
-mod debug;
-mod mouse;
-mod time;
-mod tools;
-mod unit;
-
+use rnote::{board, camera, debug, tools, unit};
 
 fn main() {
     let mut app = App::new();
@@ -27,5 +19,3 @@ fn main() {
 
     app.run();
 }
-
-
@@ -1,5 +1,201 @@
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
 
+use super::{Tool, ToolBox};
+
+const PRESET_KEYS: [KeyCode; 4] = [
+    KeyCode::Digit1,
+    KeyCode::Digit2,
+    KeyCode::Digit3,
+    KeyCode::Digit4,
+];
+const WIDTH_STEP: f32 = 1.25;
+const WIDTH_RANGE: std::ops::Range<f32> = 0.5..200.0;
+const OPACITY_STEP: f32 = 0.1;
+const PALETTE: [Color; 6] = [
+    Color::PURPLE,
+    Color::BLACK,
+    Color::MIDNIGHT_BLUE,
+    Color::CRIMSON,
+    Color::DARK_GREEN,
+    Color::GOLD,
+];
+
+/// What kind of tool drew a stroke, it decides how the stroke is rendered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum BrushKind {
+    /// Round tip, width follows the pressure.
+    #[default]
+    Pen,
+    /// Grainy translucent ink, width follows the pressure.
+    Pencil,
+    /// Wide translucent ink, multiplied with and drawn under other ink.
+    Highlighter,
+    /// Flat tip with a constant width.
+    Marker,
+}
+
+impl BrushKind {
+    pub fn pressure_sensitive(self) -> bool {
+        matches!(self, BrushKind::Pen | BrushKind::Pencil)
+    }
+    pub fn flat_tip(self) -> bool {
+        matches!(self, BrushKind::Marker)
+    }
+}
+
+/// How a stroke looks, every stroke keeps a copy of the style it was drawn with.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StrokeStyle {
+    pub kind: BrushKind,
+    pub color: Color,
+    /// Line width in board units, at full pressure.
+    pub width: f32,
+    pub opacity: f32,
+}
+
+impl Default for StrokeStyle {
+    fn default() -> Self {
+        Self {
+            kind: BrushKind::Pen,
+            color: Color::PURPLE,
+            width: 6.0,
+            opacity: 1.0,
+        }
+    }
+}
+
+impl StrokeStyle {
+    /// Color of the ink with the opacity applied.
+    pub fn ink(&self) -> Color {
+        self.color.with_a(self.color.a() * self.opacity)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BrushPreset {
+    pub name: &'static str,
+    pub style: StrokeStyle,
+}
+
+#[derive(Debug)]
 pub struct Brush {
+    pub presets: Vec<BrushPreset>,
+    pub current_preset_index: usize,
+}
+
+impl Default for Brush {
+    fn default() -> Self {
+        Self {
+            presets: vec![
+                BrushPreset {
+                    name: "pen",
+                    style: StrokeStyle::default(),
+                },
+                BrushPreset {
+                    name: "pencil",
+                    style: StrokeStyle {
+                        kind: BrushKind::Pencil,
+                        color: Color::DARK_GRAY,
+                        width: 4.0,
+                        opacity: 0.6,
+                    },
+                },
+                BrushPreset {
+                    name: "highlighter",
+                    style: StrokeStyle {
+                        kind: BrushKind::Highlighter,
+                        color: Color::YELLOW,
+                        width: 24.0,
+                        opacity: 0.5,
+                    },
+                },
+                BrushPreset {
+                    name: "marker",
+                    style: StrokeStyle {
+                        kind: BrushKind::Marker,
+                        color: Color::MIDNIGHT_BLUE,
+                        width: 10.0,
+                        opacity: 1.0,
+                    },
+                },
+            ],
+            current_preset_index: 0,
+        }
+    }
+}
+
+impl Brush {
+    /// Style of the current preset, new strokes are drawn with it.
+    pub fn style(&self) -> StrokeStyle {
+        self.presets
+            .get(self.current_preset_index)
+            .map(|preset| preset.style)
+            .unwrap_or_default()
+    }
+    pub fn current_preset_mut(&mut self) -> Option<&mut BrushPreset> {
+        self.presets.get_mut(self.current_preset_index)
+    }
+    pub fn select_preset(&mut self, index: usize) {
+        if index < self.presets.len() {
+            self.current_preset_index = index;
+        }
+    }
+    pub fn next_preset(&mut self) {
+        if self.presets.is_empty() {
+            return;
+        };
+        self.current_preset_index = (self.current_preset_index + 1) % self.presets.len()
+    }
+}
 
+/// Switch and tune brush presets while the brush is the current tool.
+///
+/// `B` cycles presets, `1`-`4` pick one, `[` and `]` change the width,
+/// `-` and `=` change the opacity and `C` cycles the color.
+pub fn brush_settings_system(mut tool_box: ResMut<ToolBox>, kbd_input: Res<ButtonInput<KeyCode>>) {
+    let Some(Tool::Brush(brush)) = tool_box.current_tool_mut() else {
+        return;
+    };
+    for (index, key) in PRESET_KEYS.into_iter().enumerate() {
+        if kbd_input.just_pressed(key) {
+            brush.select_preset(index);
+        }
+    }
+    if kbd_input.just_pressed(KeyCode::KeyB) {
+        brush.next_preset();
+    }
+    let Some(preset) = brush.current_preset_mut() else {
+        return;
+    };
+    let style = &mut preset.style;
+    if kbd_input.just_pressed(KeyCode::BracketLeft) {
+        style.width = (style.width / WIDTH_STEP).max(WIDTH_RANGE.start);
+    }
+    if kbd_input.just_pressed(KeyCode::BracketRight) {
+        style.width = (style.width * WIDTH_STEP).min(WIDTH_RANGE.end);
+    }
+    if kbd_input.just_pressed(KeyCode::Minus) {
+        style.opacity = (style.opacity - OPACITY_STEP).max(OPACITY_STEP);
+    }
+    if kbd_input.just_pressed(KeyCode::Equal) {
+        style.opacity = (style.opacity + OPACITY_STEP).min(1.0);
+    }
+    if kbd_input.just_pressed(KeyCode::KeyC) {
+        let next = PALETTE
+            .iter()
+            .position(|&color| color == style.color)
+            .map_or(0, |i| (i + 1) % PALETTE.len());
+        style.color = PALETTE[next];
+    }
+    if kbd_input.any_just_pressed(PRESET_KEYS.into_iter().chain([
+        KeyCode::KeyB,
+        KeyCode::BracketLeft,
+        KeyCode::BracketRight,
+        KeyCode::Minus,
+        KeyCode::Equal,
+        KeyCode::KeyC,
+    ])) {
+        info!("Current brush: {} {:?}", preset.name, preset.style);
+    }
 }
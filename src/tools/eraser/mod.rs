@@ -0,0 +1,160 @@
+use bevy::{prelude::*, window::PrimaryWindow};
+
+use crate::{
+    camera::Global2DCamera,
+    unit::{
+        index::UnitIndex,
+        stroke::{PressureSettings, Stroke, StrokeGroup},
+        Active,
+    },
+};
+
+use super::{
+    picker::region::{self, Region},
+    Tool, ToolBox,
+};
+
+const RADIUS_STEP: f32 = 1.25;
+const RADIUS_RANGE: std::ops::Range<f32> = 2.0..100.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EraserMode {
+    /// Delete every stroke the eraser touches.
+    #[default]
+    Stroke,
+    /// Remove only the ink under the eraser, splitting strokes into pieces.
+    Partial,
+}
+
+#[derive(Debug)]
+pub struct Eraser {
+    pub mode: EraserMode,
+    /// Radius in screen pixels.
+    pub radius: f32,
+    last_position: Option<Vec2>,
+}
+
+impl Default for Eraser {
+    fn default() -> Self {
+        Self {
+            mode: EraserMode::default(),
+            radius: 10.0,
+            last_position: None,
+        }
+    }
+}
+
+pub fn erase_system(
+    mut commands: Commands,
+    mut tool_box: ResMut<ToolBox>,
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    kbd_input: Res<ButtonInput<KeyCode>>,
+    pressure_settings: Res<PressureSettings>,
+    mut gizmos: Gizmos,
+    mut cursor_moved_events: EventReader<CursorMoved>,
+    q_window: Query<&Window, With<PrimaryWindow>>,
+    q_camera: Query<(&Camera, &GlobalTransform, &OrthographicProjection), With<Global2DCamera>>,
+    unit_index: Res<UnitIndex>,
+    mut q_group: Query<(&mut StrokeGroup, &mut Region, &GlobalTransform), Without<Active>>,
+) {
+    let Some(Tool::Eraser(eraser)) = tool_box.current_tool_mut() else {
+        cursor_moved_events.clear();
+        return;
+    };
+    if kbd_input.just_pressed(KeyCode::BracketLeft) {
+        eraser.radius = (eraser.radius / RADIUS_STEP).max(RADIUS_RANGE.start);
+    }
+    if kbd_input.just_pressed(KeyCode::BracketRight) {
+        eraser.radius = (eraser.radius * RADIUS_STEP).min(RADIUS_RANGE.end);
+    }
+    if kbd_input.just_pressed(KeyCode::KeyM) {
+        eraser.mode = match eraser.mode {
+            EraserMode::Stroke => EraserMode::Partial,
+            EraserMode::Partial => EraserMode::Stroke,
+        };
+        info!("eraser mode {:?}", eraser.mode);
+    }
+    let (camera, camera_gt, projection) = q_camera.single();
+    let Some(cursor) = q_window
+        .single()
+        .cursor_position()
+        .and_then(|p| camera.viewport_to_world_2d(camera_gt, p))
+    else {
+        return;
+    };
+    let radius = eraser.radius * projection.scale;
+    let color = match eraser.mode {
+        EraserMode::Stroke => Color::GRAY,
+        EraserMode::Partial => Color::ORANGE_RED,
+    };
+    gizmos.circle_2d(cursor, radius, color);
+    if !mouse_input.pressed(MouseButton::Left) {
+        eraser.last_position = None;
+        cursor_moved_events.clear();
+        return;
+    }
+    // every position the cursor went through since the last frame, no more than a radius apart
+    let mut path = eraser.last_position.into_iter().collect::<Vec<_>>();
+    path.extend(
+        cursor_moved_events
+            .read()
+            .filter_map(|event| camera.viewport_to_world_2d(camera_gt, event.position)),
+    );
+    path.push(cursor);
+    eraser.last_position = Some(cursor);
+    let mut samples = vec![path[0]];
+    for segment in path.windows(2) {
+        let steps = (segment[0].distance(segment[1]) / radius).ceil().max(1.0) as usize;
+        samples.extend(
+            (1..=steps).map(|step| segment[0].lerp(segment[1], step as f32 / steps as f32)),
+        );
+    }
+    let reach = samples
+        .iter()
+        .fold(Rect::from_corners(samples[0], samples[0]), |rect, &p| {
+            rect.union_point(p)
+        })
+        .inset(radius);
+    for entity in unit_index.query_rect(reach) {
+        let Ok((mut stroke_group, mut region, gt)) = q_group.get_mut(entity) else {
+            continue;
+        };
+        let to_local = gt.affine().inverse();
+        let local_radius = radius / region::local_scale(gt);
+        let local_samples = samples
+            .iter()
+            .map(|p| to_local.transform_point3(p.extend(0.0)).truncate())
+            .collect::<Vec<_>>();
+        let hit = |stroke: &Stroke| {
+            local_samples
+                .iter()
+                .any(|&p| stroke.hit(p, local_radius, &pressure_settings))
+        };
+        if !stroke_group.strokes.iter().any(hit) {
+            continue;
+        }
+        match eraser.mode {
+            EraserMode::Stroke => stroke_group.strokes.retain(|stroke| !hit(stroke)),
+            EraserMode::Partial => {
+                let strokes = std::mem::take(&mut stroke_group.strokes);
+                for stroke in strokes {
+                    if !hit(&stroke) {
+                        stroke_group.strokes.push(stroke);
+                        continue;
+                    }
+                    stroke_group.strokes.extend(stroke.cut(
+                        &local_samples,
+                        local_radius,
+                        &pressure_settings,
+                    ));
+                }
+            }
+        }
+        if stroke_group.strokes.is_empty() {
+            commands.entity(entity).despawn_recursive();
+            debug!("erased stroke group {entity:?}");
+        } else {
+            region.rect = stroke_group.bounds(&pressure_settings);
+        }
+    }
+}
@@ -1,4 +1,5 @@
 pub mod brush;
+pub mod eraser;
 pub mod picker;
 use bevy::input::keyboard::KeyboardInput;
 pub use bevy::prelude::*;
@@ -7,14 +8,34 @@ pub struct ToolPlugin;
 impl Plugin for ToolPlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<ToolBox>()
+            .init_resource::<picker::transform::TransformHistory>()
+            .init_resource::<picker::clipboard::UnitClipboard>()
+            .add_event::<picker::transform::TransformOp>()
             .add_systems(Update, switch_tool)
-            .add_systems(Update, picker::pick_unit_system);
+            .add_systems(Update, brush::brush_settings_system)
+            .add_systems(Update, picker::pick_unit_system)
+            .add_systems(
+                Update,
+                (
+                    picker::clipboard::clipboard_system,
+                    picker::grouping::grouping_system,
+                    picker::merge::merge_split_system,
+                    picker::style::restyle_system,
+                    picker::transform::transform_selection_system,
+                    picker::transform::transform_history_system,
+                    picker::highlight::selection_highlight_system,
+                )
+                    .chain()
+                    .after(picker::pick_unit_system),
+            )
+            .add_systems(Update, eraser::erase_system);
     }
 }
 #[derive(Debug)]
 pub enum Tool {
     Picker(picker::Picker),
-    Brush {},
+    Brush(brush::Brush),
+    Eraser(eraser::Eraser),
 }
 
 #[allow(clippy::derivable_impls)]
@@ -34,7 +55,11 @@ impl Default for ToolBox {
     fn default() -> Self {
         Self {
             current_tool_index: 0,
-            tools: vec![Tool::default(), Tool::Brush {}],
+            tools: vec![
+                Tool::default(),
+                Tool::Brush(Default::default()),
+                Tool::Eraser(Default::default()),
+            ],
         }
     }
 }
@@ -59,6 +84,20 @@ impl ToolBox {
         }
         self.tools.get(self.current_tool_index)
     }
+    /// The picker and its selection, whichever tool is current.
+    pub fn picker(&self) -> Option<&picker::Picker> {
+        self.tools.iter().find_map(|tool| match tool {
+            Tool::Picker(picker) => Some(picker),
+            _ => None,
+        })
+    }
+    /// The brush and its presets, whichever tool is current.
+    pub fn brush(&self) -> Option<&brush::Brush> {
+        self.tools.iter().find_map(|tool| match tool {
+            Tool::Brush(brush) => Some(brush),
+            _ => None,
+        })
+    }
     pub fn current_tool_mut(&mut self) -> Option<&mut Tool> {
         if self.tools.is_empty() {
             return None;
@@ -0,0 +1,55 @@
+//! Rubber band selection.
+use bevy::prelude::*;
+
+use crate::unit::stroke::StrokeGroup;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BandMode {
+    /// Units with any ink inside the band are selected.
+    Intersect,
+    /// Only units with all of their ink inside the band are selected.
+    Contain,
+}
+
+/// Whether a band covering the world `rect` selects a group placed at `gt`.
+pub fn selects(group: &StrokeGroup, gt: &GlobalTransform, rect: Rect, mode: BandMode) -> bool {
+    let mut curves = group.strokes.iter().map(|stroke| {
+        stroke
+            .curve()
+            .iter()
+            .map(|m| gt.transform_point(m.point.extend(0.0)).truncate())
+            .collect::<Vec<_>>()
+    });
+    match mode {
+        BandMode::Contain => curves.all(|curve| curve.iter().all(|&p| rect.contains(p))),
+        BandMode::Intersect => curves.any(|curve| match curve.as_slice() {
+            [p] => rect.contains(*p),
+            curve => curve
+                .windows(2)
+                .any(|w| segment_intersects_rect(w[0], w[1], rect)),
+        }),
+    }
+}
+
+/// Liang-Barsky clipping of the segment `a`-`b`, true when some of it is left.
+fn segment_intersects_rect(a: Vec2, b: Vec2, rect: Rect) -> bool {
+    let d = b - a;
+    let (mut enter, mut leave) = (0.0f32, 1.0f32);
+    for (p, q) in [
+        (-d.x, a.x - rect.min.x),
+        (d.x, rect.max.x - a.x),
+        (-d.y, a.y - rect.min.y),
+        (d.y, rect.max.y - a.y),
+    ] {
+        if p == 0.0 {
+            if q < 0.0 {
+                return false;
+            }
+        } else if p < 0.0 {
+            enter = enter.max(q / p);
+        } else {
+            leave = leave.min(q / p);
+        }
+    }
+    enter <= leave
+}
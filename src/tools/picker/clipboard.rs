@@ -0,0 +1,235 @@
+//! Deleting, duplicating and copying the selection.
+//!
+//! `Delete` or `Backspace` delete the selected units and strokes. `Ctrl` + `D` duplicates the
+//! selected units, `Ctrl` + `C` and `Ctrl` + `X` copy and cut them, and `Ctrl` + `V` pastes them
+//! at the cursor. Copied units also go to the system clipboard, as JSON.
+use bevy::{math::Affine3A, prelude::*, utils::HashMap, window::PrimaryWindow};
+
+use crate::{
+    board::Board,
+    camera::Global2DCamera,
+    tools::{Tool, ToolBox},
+    unit::{
+        group::{unit_group_bundle, UnitTree},
+        stroke::{
+            snapshot::{GroupSnapshot, TransformSnapshot, UnitGroupSnapshot, UnitsSnapshot},
+            stroke_group_bundle, PressureSettings, StrokeGroup,
+        },
+    },
+};
+
+use super::{region::Region, SelectionOp};
+
+/// How far duplicates are moved from the original, in screen pixels.
+const DUPLICATE_OFFSET: Vec2 = Vec2::new(16.0, -16.0);
+
+/// Units copied last, pasted when the system clipboard holds no units.
+#[derive(Debug, Default, Resource)]
+pub struct UnitClipboard {
+    snapshot: Option<UnitsSnapshot>,
+}
+
+/// Copies of units and of everything inside the unit groups among them.
+fn take_snapshot(
+    units: impl IntoIterator<Item = Entity>,
+    unit_tree: &UnitTree,
+    q_group: &Query<(&mut StrokeGroup, &mut Region, &GlobalTransform)>,
+    q_gt: &Query<&GlobalTransform>,
+) -> (Vec<GroupSnapshot>, Vec<UnitGroupSnapshot>) {
+    let (mut groups, mut unit_groups) = (Vec::new(), Vec::new());
+    for entity in units {
+        if unit_tree.is_group(entity) {
+            let Ok(gt) = q_gt.get(entity) else {
+                continue;
+            };
+            let (inner, inner_groups) =
+                take_snapshot(unit_tree.members(entity), unit_tree, q_group, q_gt);
+            unit_groups.push(UnitGroupSnapshot {
+                transform: gt.compute_transform().into(),
+                groups: inner,
+                unit_groups: inner_groups,
+            });
+        } else if let Ok((stroke_group, _, gt)) = q_group.get(entity) {
+            groups.push(GroupSnapshot::new(stroke_group, gt.compute_transform()));
+        }
+    }
+    (groups, unit_groups)
+}
+
+/// Spawn copies of units moved by `offset` in world space, under a parent placed at
+/// `parent_affine`. Returns the new units.
+fn spawn_snapshot(
+    commands: &mut Commands,
+    groups: &[GroupSnapshot],
+    unit_groups: &[UnitGroupSnapshot],
+    offset: Vec2,
+    parent: (Entity, Affine3A),
+    pressure: &PressureSettings,
+) -> Vec<Entity> {
+    let (parent, parent_affine) = parent;
+    let to_parent = parent_affine.inverse();
+    let place = |snapshot: &TransformSnapshot| {
+        let mut world = Transform::from(snapshot);
+        world.translation += offset.extend(0.0);
+        let world = world.compute_affine();
+        (world, Transform::from_matrix((to_parent * world).into()))
+    };
+    let mut units = Vec::new();
+    for group in groups {
+        let (_, transform) = place(&group.transform);
+        let bundle = stroke_group_bundle(group.stroke_group(), transform, pressure);
+        units.push(commands.spawn(bundle).set_parent(parent).id());
+    }
+    for unit_group in unit_groups {
+        let (world, transform) = place(&unit_group.transform);
+        let id = commands
+            .spawn(unit_group_bundle(transform))
+            .set_parent(parent)
+            .id();
+        spawn_snapshot(
+            commands,
+            &unit_group.groups,
+            &unit_group.unit_groups,
+            offset,
+            (id, world),
+            pressure,
+        );
+        units.push(id);
+    }
+    units
+}
+
+/// World center of the ink of the units.
+fn snapshot_center(snapshot: &UnitsSnapshot, pressure: &PressureSettings) -> Option<Vec2> {
+    snapshot
+        .all_groups()
+        .into_iter()
+        .map(|group| {
+            Region::new(group.stroke_group().bounds(pressure))
+                .world_rect(&GlobalTransform::from(Transform::from(&group.transform)))
+        })
+        .reduce(|a, b| a.union(b))
+        .map(|rect| rect.center())
+}
+
+pub fn clipboard_system(
+    mut commands: Commands,
+    mut tool_box: ResMut<ToolBox>,
+    kbd_input: Res<ButtonInput<KeyCode>>,
+    pressure_settings: Res<PressureSettings>,
+    mut clipboard: ResMut<UnitClipboard>,
+    q_window: Query<&Window, With<PrimaryWindow>>,
+    q_camera: Query<(&Camera, &GlobalTransform, &OrthographicProjection), With<Global2DCamera>>,
+    q_board: Query<(Entity, &GlobalTransform), With<Board>>,
+    unit_tree: UnitTree,
+    q_gt: Query<&GlobalTransform>,
+    mut q_group: Query<(&mut StrokeGroup, &mut Region, &GlobalTransform)>,
+) {
+    let Some(Tool::Picker(picker)) = tool_box.current_tool_mut() else {
+        return;
+    };
+    let ctrl = kbd_input.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
+    // copying nothing would overwrite what the clipboard holds
+    let selection = ctrl && (picker.picked() || !picker.selected_strokes.is_empty());
+    let delete = kbd_input.any_just_pressed([KeyCode::Delete, KeyCode::Backspace]);
+    let copy = selection && kbd_input.just_pressed(KeyCode::KeyC);
+    let cut = selection && kbd_input.just_pressed(KeyCode::KeyX);
+    let duplicate = selection && kbd_input.just_pressed(KeyCode::KeyD);
+    let paste = ctrl && kbd_input.just_pressed(KeyCode::KeyV);
+    if !(delete || copy || cut || duplicate || paste) {
+        return;
+    }
+    let (camera, camera_gt, projection) = q_camera.single();
+    let (mut groups, unit_groups) =
+        take_snapshot(picker.selected.iter().copied(), &unit_tree, &q_group, &q_gt);
+    // single strokes are copied as a group of their own
+    let mut strokes = HashMap::<Entity, Vec<usize>>::new();
+    for &(entity, index) in &picker.selected_strokes {
+        if !picker.selected.contains(&entity) {
+            strokes.entry(entity).or_default().push(index);
+        }
+    }
+    for (&entity, indices) in strokes.iter_mut() {
+        indices.sort_unstable();
+        if let Ok((stroke_group, _, gt)) = q_group.get(entity) {
+            groups.push(GroupSnapshot::with_strokes(
+                stroke_group,
+                indices,
+                gt.compute_transform(),
+            ));
+        }
+    }
+    let selected = UnitsSnapshot::new(groups, unit_groups);
+    let count = selected.groups.len() + selected.unit_groups.len();
+    let (board, board_gt) = q_board.single();
+    let board = (board, board_gt.affine());
+    if copy || cut {
+        match arboard::Clipboard::new().and_then(|mut c| c.set_text(selected.to_json())) {
+            Ok(()) => info!("copied {count} units"),
+            Err(err) => warn!("copied {count} units, system clipboard: {err}"),
+        }
+        clipboard.snapshot = Some(selected.clone());
+    }
+    if duplicate {
+        let offset = DUPLICATE_OFFSET * projection.scale;
+        let units = spawn_snapshot(
+            &mut commands,
+            &selected.groups,
+            &selected.unit_groups,
+            offset,
+            board,
+            &pressure_settings,
+        );
+        picker.pick_units(SelectionOp::Replace, units);
+    }
+    if delete || cut {
+        for &entity in &picker.selected {
+            commands.entity(entity).despawn_recursive();
+        }
+        // single strokes, from the last one so the indices stay valid
+        for (entity, mut indices) in strokes {
+            let Ok((mut stroke_group, mut region, _)) = q_group.get_mut(entity) else {
+                continue;
+            };
+            indices.sort_unstable_by(|a, b| b.cmp(a));
+            for index in indices {
+                if index < stroke_group.strokes.len() {
+                    stroke_group.strokes.remove(index);
+                }
+            }
+            if stroke_group.strokes.is_empty() {
+                commands.entity(entity).despawn_recursive();
+            } else {
+                region.rect = stroke_group.bounds(&pressure_settings);
+            }
+        }
+        picker.clear();
+    }
+    if paste {
+        let cursor = q_window
+            .single()
+            .cursor_position()
+            .and_then(|p| camera.viewport_to_world_2d(camera_gt, p));
+        let snapshot = arboard::Clipboard::new()
+            .and_then(|mut c| c.get_text())
+            .ok()
+            .and_then(|text| UnitsSnapshot::from_json(&text))
+            .or_else(|| clipboard.snapshot.clone());
+        let (Some(cursor), Some(snapshot)) = (cursor, snapshot) else {
+            return;
+        };
+        let Some(center) = snapshot_center(&snapshot, &pressure_settings) else {
+            return;
+        };
+        let units = spawn_snapshot(
+            &mut commands,
+            &snapshot.groups,
+            &snapshot.unit_groups,
+            cursor - center,
+            board,
+            &pressure_settings,
+        );
+        info!("pasted {} units", units.len());
+        picker.pick_units(SelectionOp::Replace, units);
+    }
+}
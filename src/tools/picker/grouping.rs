@@ -0,0 +1,84 @@
+//! Grouping and ungrouping the selection.
+use bevy::prelude::*;
+
+use crate::{
+    board::Board,
+    tools::{Tool, ToolBox},
+    unit::group::{unit_group_bundle, UnitTree},
+};
+
+use super::{highlight::selection_bounds, region::Region, SelectionOp};
+
+/// `Ctrl` + `G` puts the selected units in a new group, `Ctrl` + `Shift` + `G` ungroups the
+/// selected groups. Units keep their place on the board either way.
+pub fn grouping_system(
+    mut commands: Commands,
+    mut tool_box: ResMut<ToolBox>,
+    kbd_input: Res<ButtonInput<KeyCode>>,
+    unit_tree: UnitTree,
+    q_board: Query<(Entity, &GlobalTransform), With<Board>>,
+    q_region: Query<(&Region, &GlobalTransform)>,
+    q_parent: Query<&Parent>,
+) {
+    let Some(Tool::Picker(picker)) = tool_box.current_tool_mut() else {
+        return;
+    };
+    if !kbd_input.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight])
+        || !kbd_input.just_pressed(KeyCode::KeyG)
+    {
+        return;
+    }
+    if kbd_input.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]) {
+        let groups = picker
+            .selected
+            .iter()
+            .copied()
+            .filter(|&entity| unit_tree.is_group(entity))
+            .collect::<Vec<_>>();
+        let mut members = Vec::new();
+        for group in groups {
+            picker.selected.remove(&group);
+            let parent = q_parent.get(group).map(Parent::get);
+            for member in unit_tree.members(group) {
+                match parent {
+                    Ok(parent) => commands.entity(member).set_parent_in_place(parent),
+                    Err(_) => commands.entity(member).remove_parent_in_place(),
+                };
+                members.push(member);
+            }
+            commands.entity(group).despawn_recursive();
+        }
+        if !members.is_empty() {
+            info!("ungrouped {} units", members.len());
+            picker.pick_units(SelectionOp::Add, members);
+        }
+        return;
+    }
+    if picker.selected.len() < 2 {
+        return;
+    }
+    let Some(bounds) = selection_bounds(picker, &q_region) else {
+        return;
+    };
+    let (board, board_gt) = q_board.single();
+    // the group goes on the board, at the center of its members
+    let center = board_gt
+        .affine()
+        .inverse()
+        .transform_point3(bounds.center().extend(0.0));
+    let transform = Transform::from_translation(center.truncate().extend(1.0));
+    // members are parented in place before transforms propagate, the group needs its global
+    // transform already
+    let group = commands
+        .spawn(unit_group_bundle(transform))
+        .insert(GlobalTransform::from(
+            board_gt.affine() * transform.compute_affine(),
+        ))
+        .set_parent(board)
+        .id();
+    for &member in &picker.selected {
+        commands.entity(member).set_parent_in_place(group);
+    }
+    info!("grouped {} units", picker.selected.len());
+    picker.pick_units(SelectionOp::Replace, [group]);
+}
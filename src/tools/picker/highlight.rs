@@ -0,0 +1,114 @@
+//! Outlines of the selection.
+use bevy::prelude::*;
+
+use crate::{
+    camera::Global2DCamera,
+    tools::ToolBox,
+    unit::stroke::{PressureSettings, StrokeGroup},
+};
+
+use super::{region::Region, Picker};
+
+/// Side of the square handles on the corners of the selection box, in screen pixels.
+pub const HANDLE_SIZE: f32 = 8.0;
+/// Distance of the rotation handle above the selection box, in screen pixels.
+pub const ROTATE_HANDLE_DISTANCE: f32 = 24.0;
+/// Space between the ink and its outline, in screen pixels.
+const OUTLINE_MARGIN: f32 = 4.0;
+const OUTLINE_COLOR: Color = Color::ORANGE;
+const SELECTION_COLOR: Color = Color::ORANGE_RED;
+
+/// World bounding box of every selected unit.
+pub fn selection_bounds(
+    picker: &Picker,
+    q_region: &Query<(&Region, &GlobalTransform)>,
+) -> Option<Rect> {
+    picker
+        .selected
+        .iter()
+        .filter_map(|&entity| q_region.get(entity).ok())
+        .map(|(region, gt)| region.world_rect(gt))
+        .reduce(|a, b| a.union(b))
+}
+
+/// Box around the selected units with their handles, for a camera at `scale`.
+pub fn selection_box(
+    picker: &Picker,
+    q_region: &Query<(&Region, &GlobalTransform)>,
+    scale: f32,
+) -> Option<Rect> {
+    selection_bounds(picker, q_region).map(|bounds| bounds.inset(2.0 * OUTLINE_MARGIN * scale))
+}
+
+/// Corners of a selection box, counterclockwise from the bottom left.
+pub fn box_corners(rect: Rect) -> [Vec2; 4] {
+    [
+        rect.min,
+        Vec2::new(rect.max.x, rect.min.y),
+        rect.max,
+        Vec2::new(rect.min.x, rect.max.y),
+    ]
+}
+
+/// Position of the rotation handle of a selection box, for a camera at `scale`.
+pub fn rotate_handle(rect: Rect, scale: f32) -> Vec2 {
+    Vec2::new(rect.center().x, rect.max.y + ROTATE_HANDLE_DISTANCE * scale)
+}
+
+fn outline(gizmos: &mut Gizmos, corners: [Vec2; 4], color: Color) {
+    gizmos.linestrip_2d(corners.into_iter().chain([corners[0]]), color);
+}
+
+/// Outline every selected unit and stroke, and draw the handles of the selection box.
+///
+/// Gizmo lines keep their width in pixels at any zoom.
+pub fn selection_highlight_system(
+    tool_box: Res<ToolBox>,
+    pressure_settings: Res<PressureSettings>,
+    mut gizmos: Gizmos,
+    q_camera: Query<&OrthographicProjection, With<Global2DCamera>>,
+    q_region: Query<(&Region, &GlobalTransform)>,
+    q_group: Query<&StrokeGroup>,
+) {
+    let Some(picker) = tool_box.picker() else {
+        return;
+    };
+    let scale = q_camera.single().scale;
+    let margin = OUTLINE_MARGIN * scale;
+    for &entity in &picker.selected {
+        let Ok((region, gt)) = q_region.get(entity) else {
+            continue;
+        };
+        let local_margin = margin / super::region::local_scale(gt);
+        let region = Region::new(region.rect.inset(local_margin));
+        outline(&mut gizmos, region.world_corners(gt), OUTLINE_COLOR);
+    }
+    for &(entity, index) in &picker.selected_strokes {
+        let (Ok((_, gt)), Ok(stroke_group)) = (q_region.get(entity), q_group.get(entity)) else {
+            continue;
+        };
+        let Some(bounds) = stroke_group
+            .strokes
+            .get(index)
+            .and_then(|stroke| stroke.bounds(&pressure_settings))
+        else {
+            continue;
+        };
+        let local_margin = margin / super::region::local_scale(gt);
+        let region = Region::new(bounds.inset(local_margin));
+        outline(&mut gizmos, region.world_corners(gt), OUTLINE_COLOR);
+    }
+    let Some(bounds) = selection_box(picker, &q_region, scale) else {
+        return;
+    };
+    if picker.selected.len() > 1 {
+        outline(&mut gizmos, box_corners(bounds), SELECTION_COLOR);
+    }
+    let handle = Vec2::splat(HANDLE_SIZE * scale);
+    for corner in box_corners(bounds) {
+        gizmos.rect_2d(corner, 0.0, handle, SELECTION_COLOR);
+    }
+    let rotate = rotate_handle(bounds, scale);
+    gizmos.line_2d(Vec2::new(rotate.x, bounds.max.y), rotate, SELECTION_COLOR);
+    gizmos.circle_2d(rotate, handle.x * 0.5, SELECTION_COLOR);
+}
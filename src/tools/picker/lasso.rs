@@ -0,0 +1,160 @@
+//! Freehand lasso selection.
+use bevy::prelude::*;
+
+use crate::unit::stroke::{Stroke, StrokeGroup};
+
+/// Share of the points of some ink that have to be inside the lasso to select it, hand drawn
+/// lassos tend to cut corners.
+const COVERAGE: f32 = 0.8;
+
+/// Even-odd test, the polygon is closed between its last and first point and may be concave.
+pub fn contains(polygon: &[Vec2], point: Vec2) -> bool {
+    let mut inside = false;
+    let mut previous = match polygon.last() {
+        Some(&p) => p,
+        None => return false,
+    };
+    for &p in polygon {
+        if (p.y > point.y) != (previous.y > point.y) {
+            let x = p.x + (point.y - p.y) / (previous.y - p.y) * (previous.x - p.x);
+            if point.x < x {
+                inside = !inside;
+            }
+        }
+        previous = p;
+    }
+    inside
+}
+
+/// World positions of the curve of a stroke in a group placed at `gt`.
+fn world_points<'a>(
+    stroke: &'a Stroke,
+    gt: &'a GlobalTransform,
+) -> impl Iterator<Item = Vec2> + 'a {
+    stroke
+        .curve()
+        .iter()
+        .map(|m| gt.transform_point(m.point.extend(0.0)).truncate())
+}
+
+fn covered(polygon: &[Vec2], points: impl Iterator<Item = Vec2>) -> bool {
+    let (mut inside, mut total) = (0, 0);
+    for point in points {
+        total += 1;
+        if contains(polygon, point) {
+            inside += 1;
+        }
+    }
+    total > 0 && inside as f32 >= COVERAGE * total as f32
+}
+
+/// Whether the lasso selects a stroke of a group placed at `gt`.
+pub fn selects_stroke(polygon: &[Vec2], stroke: &Stroke, gt: &GlobalTransform) -> bool {
+    covered(polygon, world_points(stroke, gt))
+}
+
+/// Whether the lasso selects a whole group placed at `gt`.
+pub fn selects(polygon: &[Vec2], group: &StrokeGroup, gt: &GlobalTransform) -> bool {
+    covered(
+        polygon,
+        group
+            .strokes
+            .iter()
+            .flat_map(|stroke| world_points(stroke, gt)),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{tools::brush::StrokeStyle, unit::stroke::PointMeasurement};
+
+    /// U shape 30 units wide, with a notch 10 units wide coming down from the top to y = 10.
+    const U: [Vec2; 8] = [
+        Vec2::new(0.0, 0.0),
+        Vec2::new(30.0, 0.0),
+        Vec2::new(30.0, 30.0),
+        Vec2::new(20.0, 30.0),
+        Vec2::new(20.0, 10.0),
+        Vec2::new(10.0, 10.0),
+        Vec2::new(10.0, 30.0),
+        Vec2::new(0.0, 30.0),
+    ];
+
+    fn group(points: impl IntoIterator<Item = Vec2>) -> StrokeGroup {
+        let mut group = StrokeGroup::new();
+        group.strokes.push(Stroke {
+            measurements: points
+                .into_iter()
+                .map(PointMeasurement::new_point)
+                .collect(),
+            ..Stroke::new(StrokeStyle::default())
+        });
+        group
+    }
+
+    /// Horizontal line of `count` points at height `y`, `inside` of them in the left arm of
+    /// the U and the others in the notch.
+    fn split_line(count: usize, inside: usize, y: f32) -> StrokeGroup {
+        group((0..count).map(|i| {
+            let x = if i < inside { 2.0 } else { 15.0 };
+            Vec2::new(x + i as f32 * 0.1, y)
+        }))
+    }
+
+    #[test]
+    fn contains_the_arms_and_base_of_a_u() {
+        assert!(contains(&U, Vec2::new(5.0, 20.0)));
+        assert!(contains(&U, Vec2::new(25.0, 20.0)));
+        assert!(contains(&U, Vec2::new(15.0, 5.0)));
+    }
+
+    #[test]
+    fn does_not_contain_the_notch_of_a_u() {
+        assert!(!contains(&U, Vec2::new(15.0, 20.0)));
+        assert!(!contains(&U, Vec2::new(15.0, 29.0)));
+        assert!(!contains(&U, Vec2::new(40.0, 5.0)));
+        assert!(!contains(&U, Vec2::new(15.0, -1.0)));
+    }
+
+    #[test]
+    fn degenerate_polygons_contain_nothing() {
+        assert!(!contains(&[], Vec2::ZERO));
+        assert!(!contains(&[Vec2::ZERO, Vec2::X], Vec2::new(0.5, 0.0)));
+    }
+
+    #[test]
+    fn selects_ink_in_an_arm_but_not_in_the_notch() {
+        let gt = GlobalTransform::IDENTITY;
+        let arm = group((0..10).map(|i| Vec2::new(5.0, 12.0 + i as f32)));
+        let notch = group((0..10).map(|i| Vec2::new(15.0, 12.0 + i as f32)));
+        assert!(selects(&U, &arm, &gt));
+        assert!(!selects(&U, &notch, &gt));
+        assert!(!selects(&U, &group([]), &gt));
+    }
+
+    #[test]
+    fn ink_across_the_notch_is_not_selected() {
+        // the bounding box of the lasso holds the whole line, the lasso only two thirds of it
+        let across = group((0..=30).map(|x| Vec2::new(x as f32, 20.0)));
+        assert!(!selects(&U, &across, &GlobalTransform::IDENTITY));
+    }
+
+    #[test]
+    fn selects_at_the_coverage_boundary() {
+        let gt = GlobalTransform::IDENTITY;
+        assert!(selects(&U, &split_line(10, 8, 20.0), &gt));
+        assert!(!selects(&U, &split_line(10, 7, 20.0), &gt));
+        let stroke = &split_line(10, 8, 20.0).strokes[0];
+        assert!(selects_stroke(&U, stroke, &gt));
+    }
+
+    #[test]
+    fn selects_in_world_space() {
+        // ink in the notch, moved into the right arm by its transform
+        let notch = group((0..10).map(|i| Vec2::new(15.0, 12.0 + i as f32)));
+        let gt = GlobalTransform::from_xyz(10.0, 0.0, 0.0);
+        assert!(selects(&U, &notch, &gt));
+        assert!(selects_stroke(&U, &notch.strokes[0], &gt));
+    }
+}
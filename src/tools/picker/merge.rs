@@ -0,0 +1,206 @@
+//! Merging stroke groups and splitting them apart.
+//!
+//! `Ctrl` + `E` merges the selected stroke groups into one. `Ctrl` + `Shift` + `E` splits the
+//! selected strokes out of their groups, or with no single strokes selected, splits every
+//! selected stroke group into clusters of strokes that are apart from each other.
+use bevy::{
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+
+use crate::{
+    camera::Global2DCamera,
+    tools::{Tool, ToolBox},
+    unit::{
+        stroke::{stroke_group_bundle, PressureSettings, Stroke, StrokeGroup},
+        Active,
+    },
+};
+
+use super::{
+    region::{self, Region},
+    transform::to_affine2,
+    SelectionOp,
+};
+
+/// Strokes further apart than this end up in different groups when splitting, in screen pixels.
+const CLUSTER_GAP: f32 = 24.0;
+
+pub fn merge_split_system(
+    mut commands: Commands,
+    mut tool_box: ResMut<ToolBox>,
+    kbd_input: Res<ButtonInput<KeyCode>>,
+    pressure_settings: Res<PressureSettings>,
+    q_camera: Query<&OrthographicProjection, With<Global2DCamera>>,
+    mut q_group: Query<
+        (
+            &mut StrokeGroup,
+            &mut Region,
+            &GlobalTransform,
+            &Transform,
+            Option<&Parent>,
+        ),
+        Without<Active>,
+    >,
+) {
+    let Some(Tool::Picker(picker)) = tool_box.current_tool_mut() else {
+        return;
+    };
+    if !kbd_input.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight])
+        || !kbd_input.just_pressed(KeyCode::KeyE)
+    {
+        return;
+    }
+    if kbd_input.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]) {
+        // the piece every stroke goes to, for every group to split
+        let splits = if picker.selected_strokes.is_empty() {
+            let gap = CLUSTER_GAP * q_camera.single().scale;
+            picker
+                .selected
+                .iter()
+                .filter_map(|&entity| {
+                    let (stroke_group, _, gt, ..) = q_group.get(entity).ok()?;
+                    let local_gap = gap / region::local_scale(gt);
+                    let pieces = clusters(&stroke_group.strokes, local_gap, &pressure_settings);
+                    Some((entity, pieces))
+                })
+                .collect::<Vec<_>>()
+        } else {
+            let mut selected = HashMap::<Entity, HashSet<usize>>::new();
+            for &(entity, index) in &picker.selected_strokes {
+                selected.entry(entity).or_default().insert(index);
+            }
+            selected
+                .into_iter()
+                .filter_map(|(entity, indices)| {
+                    let (stroke_group, ..) = q_group.get(entity).ok()?;
+                    let pieces = (0..stroke_group.strokes.len())
+                        .map(|index| indices.contains(&index) as usize)
+                        .collect();
+                    Some((entity, pieces))
+                })
+                .collect()
+        };
+        let mut units = Vec::new();
+        for (entity, pieces) in splits {
+            units.push(entity);
+            let count = pieces.iter().collect::<HashSet<_>>().len();
+            if count < 2 {
+                continue;
+            }
+            let Ok((mut stroke_group, mut region, _, transform, parent)) = q_group.get_mut(entity)
+            else {
+                continue;
+            };
+            let mut strokes = vec![Vec::new(); pieces.iter().max().map_or(0, |max| max + 1)];
+            for (stroke, piece) in std::mem::take(&mut stroke_group.strokes)
+                .into_iter()
+                .zip(pieces)
+            {
+                strokes[piece].push(stroke);
+            }
+            let mut strokes = strokes.into_iter().filter(|piece| !piece.is_empty());
+            stroke_group.strokes = strokes.next().unwrap_or_default();
+            region.rect = stroke_group.bounds(&pressure_settings);
+            for strokes in strokes {
+                let piece = StrokeGroup {
+                    strokes,
+                    active_stroke: None,
+                    started_at: stroke_group.started_at,
+                };
+                let mut piece =
+                    commands.spawn(stroke_group_bundle(piece, *transform, &pressure_settings));
+                if let Some(parent) = parent {
+                    piece.set_parent(parent.get());
+                }
+                units.push(piece.id());
+            }
+        }
+        info!("split into {} stroke groups", units.len());
+        picker.pick_units(SelectionOp::Replace, units);
+        return;
+    }
+
+    let mut merged = picker
+        .selected
+        .iter()
+        .filter_map(|&entity| Some((entity, q_group.get(entity).ok()?.0.started_at)))
+        .collect::<Vec<_>>();
+    if merged.len() < 2 {
+        return;
+    }
+    // the earliest group takes the strokes of the others, so measurement times stay positive
+    // and later ink stays on top
+    merged.sort_by_key(|&(_, started_at)| started_at);
+    let (target, target_started_at) = merged[0];
+    let Ok((.., &target_gt, _, _)) = q_group.get(target) else {
+        return;
+    };
+    let to_target = target_gt.affine().inverse();
+    let mut strokes = Vec::new();
+    for &(entity, started_at) in &merged[1..] {
+        let Ok((stroke_group, _, gt, ..)) = q_group.get(entity) else {
+            continue;
+        };
+        // points move into the local frame of the target, widths and times follow
+        let mut moved = stroke_group.clone();
+        moved.transform_points(to_affine2(to_target * gt.affine()));
+        let width_scale = region::local_scale(gt) / region::local_scale(&target_gt);
+        let delay = started_at.saturating_duration_since(target_started_at);
+        for mut stroke in moved.strokes {
+            stroke.style.width *= width_scale;
+            for m in stroke
+                .measurements
+                .iter_mut()
+                .chain(stroke.curve.iter_mut())
+            {
+                m.time += delay;
+            }
+            strokes.push(stroke);
+        }
+        commands.entity(entity).despawn_recursive();
+    }
+    if let Ok((mut stroke_group, mut region, ..)) = q_group.get_mut(target) {
+        stroke_group.strokes.extend(strokes);
+        region.rect = stroke_group.bounds(&pressure_settings);
+    }
+    info!("merged {} stroke groups", merged.len());
+    picker.pick_units(SelectionOp::Replace, [target]);
+}
+
+/// The cluster of every stroke, numbered in stroke order. Strokes whose ink is closer than
+/// `gap` are in the same cluster.
+fn clusters(strokes: &[Stroke], gap: f32, pressure: &PressureSettings) -> Vec<usize> {
+    let bounds = strokes
+        .iter()
+        .map(|stroke| stroke.bounds(pressure).map(|rect| rect.inset(gap * 0.5)))
+        .collect::<Vec<_>>();
+    let touching = |a: usize, b: usize| match (bounds[a], bounds[b]) {
+        (Some(a), Some(b)) => !a.intersect(b).is_empty(),
+        _ => false,
+    };
+    // every stroke takes the lowest index among the strokes it touches, until none changes
+    let mut lowest = (0..strokes.len()).collect::<Vec<_>>();
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for a in 0..strokes.len() {
+            for b in a + 1..strokes.len() {
+                if lowest[a] != lowest[b] && touching(a, b) {
+                    let min = lowest[a].min(lowest[b]);
+                    lowest[a] = min;
+                    lowest[b] = min;
+                    changed = true;
+                }
+            }
+        }
+    }
+    let mut numbers = HashMap::new();
+    lowest
+        .into_iter()
+        .map(|index| {
+            let next = numbers.len();
+            *numbers.entry(index).or_insert(next)
+        })
+        .collect()
+}
@@ -1,14 +1,84 @@
+pub mod band;
+pub mod clipboard;
+pub mod grouping;
+pub mod highlight;
+pub mod lasso;
+pub mod merge;
 pub mod region;
-use bevy::{input::keyboard::KeyboardInput, prelude::*, window::PrimaryWindow};
-use region::Region;
+pub mod style;
+pub mod transform;
+use bevy::{prelude::*, utils::HashSet, window::PrimaryWindow};
+use std::hash::Hash;
 
-use crate::{camera::Global2DCamera, unit::Unit};
+use crate::{
+    camera::Global2DCamera,
+    unit::{
+        group::UnitTree,
+        index::UnitIndex,
+        stroke::{PressureSettings, StrokeGroup},
+        Unit,
+    },
+};
 
 use super::{Tool, ToolBox};
+use band::BandMode;
+
+/// How far from the ink a click still picks a unit, in screen pixels.
+const PICK_RADIUS: f32 = 4.0;
+/// Distance between two points of a lasso, in screen pixels.
+const LASSO_STEP: f32 = 4.0;
 
 #[derive(Debug, Default)]
 pub struct Picker {
-    pub selected: Vec<Entity>,
+    pub selected: HashSet<Entity>,
+    /// Single strokes picked with the lasso, as a group and an index into its strokes.
+    pub selected_strokes: HashSet<(Entity, usize)>,
+    /// Drags on empty space draw a lasso instead of a rubber band.
+    pub lasso: bool,
+    /// Scaling moves the ink of stroke groups instead of scaling their transform, so the
+    /// lines keep their width.
+    pub keep_stroke_width: bool,
+    drag: Option<PickerDrag>,
+}
+
+/// What a left drag with the picker is doing.
+#[derive(Debug)]
+enum PickerDrag {
+    /// Drawing a selection rectangle from the world position `start`.
+    Band { start: Vec2 },
+    /// Drawing a lasso through world positions.
+    Lasso { points: Vec<Vec2> },
+    /// Moving, scaling or rotating the selected units with the cursor since the world
+    /// position `origin`.
+    Transform {
+        kind: transform::TransformKind,
+        origin: Vec2,
+        /// Selected unit that was clicked without changing the selection.
+        clicked: Option<Entity>,
+        /// Transforms of the units before the drag.
+        start: Vec<(Entity, Transform)>,
+    },
+}
+
+/// How picked units change the selection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SelectionOp {
+    Replace,
+    Add,
+    Toggle,
+}
+
+impl SelectionOp {
+    /// `Shift` adds to the selection, `Ctrl` toggles, otherwise the selection is replaced.
+    fn from_keys(kbd_input: &ButtonInput<KeyCode>) -> Self {
+        if kbd_input.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]) {
+            SelectionOp::Add
+        } else if kbd_input.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]) {
+            SelectionOp::Toggle
+        } else {
+            SelectionOp::Replace
+        }
+    }
 }
 
 impl Picker {
@@ -16,38 +86,288 @@ impl Picker {
     pub fn picked(&self) -> bool {
         !self.selected.is_empty()
     }
+    pub fn clear(&mut self) {
+        self.selected.clear();
+        self.selected_strokes.clear();
+    }
+    /// Replacing with no units clears the selection.
+    pub fn pick_units(&mut self, op: SelectionOp, units: impl IntoIterator<Item = Entity>) {
+        if op == SelectionOp::Replace {
+            self.clear();
+        }
+        update_selection(&mut self.selected, op, units);
+    }
+    pub fn pick_strokes(
+        &mut self,
+        op: SelectionOp,
+        strokes: impl IntoIterator<Item = (Entity, usize)>,
+    ) {
+        if op == SelectionOp::Replace {
+            self.clear();
+        }
+        update_selection(&mut self.selected_strokes, op, strokes);
+    }
+}
+
+fn update_selection<T: Eq + Hash>(
+    selection: &mut HashSet<T>,
+    op: SelectionOp,
+    items: impl IntoIterator<Item = T>,
+) {
+    for item in items {
+        if op == SelectionOp::Toggle && selection.remove(&item) {
+            continue;
+        }
+        selection.insert(item);
+    }
 }
 
+/// Pick units with the left mouse button.
+///
+/// A click on ink picks the topmost unit under it, dragging it moves the selection. The handles
+/// of the selection box scale and rotate it. A drag starting on empty space draws a
+/// rubber band, which selects the units it touches, or with `Alt` held the units entirely inside.
+/// `L` switches to a lasso, which selects the units inside it, or with `Alt` held single strokes.
+///
+/// Picks replace the selection, with `Shift` held they add to it and with `Ctrl` held they
+/// toggle. A click on empty space or `Escape` clears the selection, `Ctrl` + `A` selects all.
+/// Grouped units are picked as their outermost group.
 pub fn pick_unit_system(
     // these will panic if the resources don't exist
     mut tool_box: ResMut<ToolBox>,
-    mut mouse_input: Res<ButtonInput<MouseButton>>,
-    mut kbd_input: Res<ButtonInput<KeyCode>>,
-    mut q_window: Query<&Window, With<PrimaryWindow>>,
-    mut q_unit: Query<(Entity, &GlobalTransform, &Region, &Unit)>,
-    q_camera: Query<(&Camera, &GlobalTransform), With<Global2DCamera>>,
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    kbd_input: Res<ButtonInput<KeyCode>>,
+    pressure_settings: Res<PressureSettings>,
+    unit_index: Res<UnitIndex>,
+    unit_tree: UnitTree,
+    mut gizmos: Gizmos,
+    q_window: Query<&Window, With<PrimaryWindow>>,
+    q_unit: Query<(Entity, &GlobalTransform, &Unit, &StrokeGroup)>,
+    q_transform: Query<&Transform>,
+    q_region: Query<(&region::Region, &GlobalTransform)>,
+    q_camera: Query<(&Camera, &GlobalTransform, &OrthographicProjection), With<Global2DCamera>>,
 ) {
     let picker = match tool_box.current_tool_mut() {
         Some(Tool::Picker(p)) => p,
         _ => return,
     };
-    let (camera, camera_gt) = q_camera.single();
+    let (camera, camera_gt, projection) = q_camera.single();
     let window = q_window.single();
-    let Some(cursor_position) = window.cursor_position() else {
+    let Some(mouse_position) = window
+        .cursor_position()
+        .and_then(|p| camera.viewport_to_world_2d(camera_gt, p))
+    else {
         return;
     };
+    let radius = PICK_RADIUS * projection.scale;
+    let alt = kbd_input.any_pressed([KeyCode::AltLeft, KeyCode::AltRight]);
+    if kbd_input.just_pressed(KeyCode::KeyL) {
+        picker.lasso = !picker.lasso;
+        info!("picker lasso {}", picker.lasso);
+    }
+    if kbd_input.just_pressed(KeyCode::KeyW) {
+        picker.keep_stroke_width = !picker.keep_stroke_width;
+        info!("picker keeps stroke width {}", picker.keep_stroke_width);
+    }
+    // units may have been erased or merged since they were selected
+    picker.selected.retain(|&entity| unit_tree.is_unit(entity));
+    picker.selected_strokes.retain(|&(entity, index)| {
+        q_unit
+            .get(entity)
+            .is_ok_and(|(.., stroke_group)| index < stroke_group.strokes.len())
+    });
+    let op = SelectionOp::from_keys(&kbd_input);
+    // escape cancels a drag before it clears the selection, moves are put back by
+    // `transform_selection_system`
+    if kbd_input.just_pressed(KeyCode::Escape) {
+        match picker.drag {
+            None => picker.clear(),
+            Some(PickerDrag::Transform { .. }) => {}
+            Some(_) => picker.drag = None,
+        }
+    }
+    if kbd_input.just_pressed(KeyCode::KeyA)
+        && kbd_input.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight])
+    {
+        picker.pick_units(
+            SelectionOp::Replace,
+            q_unit
+                .iter()
+                .map(|(entity, ..)| unit_tree.outermost(entity))
+                .collect::<HashSet<_>>(),
+        );
+    }
 
-    if mouse_input.just_pressed(MouseButton::Left) {
-        let mouse_position = match camera.viewport_to_world_2d(camera_gt, cursor_position) {
-            Some(p) => p,
-            None => return,
-        };
-        for (entity, gt, region, unit) in q_unit.iter_mut() {
-            let base_position = gt.translation().truncate();
-            let mouse_position = mouse_position - base_position;
-            if region.rect.contains(mouse_position) {
-                picker.selected.push(entity)
+    let start_transforms = |picker: &Picker| {
+        picker
+            .selected
+            .iter()
+            .filter_map(|&entity| Some((entity, *q_transform.get(entity).ok()?)))
+            .collect::<Vec<_>>()
+    };
+    let handle = mouse_input
+        .just_pressed(MouseButton::Left)
+        .then(|| highlight::selection_box(picker, &q_region, projection.scale))
+        .flatten()
+        .and_then(|bounds| transform::grabbed_handle(bounds, mouse_position, projection.scale));
+    if let Some(kind) = handle {
+        picker.drag = Some(PickerDrag::Transform {
+            kind,
+            origin: mouse_position,
+            clicked: None,
+            start: start_transforms(picker),
+        });
+    } else if mouse_input.just_pressed(MouseButton::Left) {
+        // the index finds the units around the cursor, their strokes decide
+        let topmost = unit_index
+            .query_point(mouse_position, radius)
+            .filter_map(|entity| q_unit.get(entity).ok())
+            .filter_map(|(entity, gt, unit, stroke_group)| {
+                let local_position = region::to_local(gt, mouse_position);
+                let local_radius = radius / region::local_scale(gt);
+                stroke_group
+                    .hit(local_position, local_radius, &pressure_settings)
+                    .then_some((entity, unit.layer(), gt.translation().z, stroke_group))
+            })
+            .max_by(|a, b| {
+                a.1.cmp(&b.1)
+                    .then(a.2.total_cmp(&b.2))
+                    .then(a.3.started_at.cmp(&b.3.started_at))
+            });
+        match topmost.map(|(entity, ..)| unit_tree.outermost(entity)) {
+            Some(entity) => {
+                // grabbing a selected unit keeps the selection, so all of it can be moved
+                let grabbed = op == SelectionOp::Replace && picker.selected.contains(&entity);
+                if !grabbed {
+                    picker.pick_units(op, [entity]);
+                }
+                if picker.selected.contains(&entity) {
+                    picker.drag = Some(PickerDrag::Transform {
+                        kind: transform::TransformKind::Move,
+                        origin: mouse_position,
+                        clicked: grabbed.then_some(entity),
+                        start: start_transforms(picker),
+                    });
+                }
+            }
+            None if picker.lasso => {
+                picker.drag = Some(PickerDrag::Lasso {
+                    points: vec![mouse_position],
+                })
+            }
+            None => {
+                picker.drag = Some(PickerDrag::Band {
+                    start: mouse_position,
+                })
             }
         }
     }
+
+    match &mut picker.drag {
+        Some(PickerDrag::Band { start }) => {
+            let rect = Rect::from_corners(*start, mouse_position);
+            let mode = if alt {
+                BandMode::Contain
+            } else {
+                BandMode::Intersect
+            };
+            if mouse_input.pressed(MouseButton::Left) {
+                let color = match mode {
+                    BandMode::Intersect => Color::ORANGE,
+                    BandMode::Contain => Color::CYAN,
+                };
+                gizmos.rect_2d(rect.center(), 0.0, rect.size(), color);
+                return;
+            }
+            picker.drag = None;
+            // a click on empty space is not a band, it picks nothing
+            if rect.size().max_element() <= radius {
+                picker.pick_units(op, []);
+                return;
+            }
+            let selects = |entity| {
+                q_unit.get(entity).is_ok_and(|(_, gt, _, stroke_group)| {
+                    band::selects(stroke_group, gt, rect, mode)
+                })
+            };
+            let units = grouped_units(
+                unit_index.query_rect(rect),
+                &unit_tree,
+                |leaves| match mode {
+                    BandMode::Intersect => leaves.iter().any(|&leaf| selects(leaf)),
+                    BandMode::Contain => leaves.iter().all(|&leaf| selects(leaf)),
+                },
+            );
+            picker.pick_units(op, units);
+        }
+        Some(PickerDrag::Lasso { points }) => {
+            if points
+                .last()
+                .is_some_and(|last| last.distance(mouse_position) >= LASSO_STEP * projection.scale)
+            {
+                points.push(mouse_position);
+            }
+            if mouse_input.pressed(MouseButton::Left) {
+                let color = if alt { Color::CYAN } else { Color::ORANGE };
+                gizmos.linestrip_2d(points.iter().copied().chain(points.first().copied()), color);
+                return;
+            }
+            let Some(PickerDrag::Lasso { points }) = picker.drag.take() else {
+                return;
+            };
+            // a click on empty space is not a lasso, it picks nothing
+            if points.len() < 3 {
+                picker.pick_units(op, []);
+                return;
+            }
+            let bounds = points
+                .iter()
+                .fold(Rect::from_corners(points[0], points[0]), |rect, &p| {
+                    rect.union_point(p)
+                });
+            if alt {
+                let strokes = unit_index
+                    .query_rect(bounds)
+                    .filter_map(|entity| q_unit.get(entity).ok())
+                    .flat_map(|(entity, gt, _, stroke_group)| {
+                        stroke_group
+                            .strokes
+                            .iter()
+                            .enumerate()
+                            .filter(|(_, stroke)| lasso::selects_stroke(&points, stroke, gt))
+                            .map(move |(index, _)| (entity, index))
+                    })
+                    .collect::<Vec<_>>();
+                picker.pick_strokes(op, strokes);
+            } else {
+                let selects = |entity| {
+                    q_unit.get(entity).is_ok_and(|(_, gt, _, stroke_group)| {
+                        lasso::selects(&points, stroke_group, gt)
+                    })
+                };
+                let units = grouped_units(unit_index.query_rect(bounds), &unit_tree, |leaves| {
+                    leaves.iter().all(|&leaf| selects(leaf))
+                });
+                picker.pick_units(op, units);
+            }
+        }
+        Some(PickerDrag::Transform { .. }) | None => {}
+    }
+}
+
+/// The outermost groups, or ungrouped units, around `candidates` whose leaves pass `selects`.
+fn grouped_units(
+    candidates: impl Iterator<Item = Entity>,
+    unit_tree: &UnitTree,
+    selects: impl Fn(&[Entity]) -> bool,
+) -> Vec<Entity> {
+    candidates
+        .map(|entity| unit_tree.outermost(entity))
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .filter(|&root| {
+            let leaves = unit_tree.leaves(root);
+            !leaves.is_empty() && selects(&leaves)
+        })
+        .collect()
 }
@@ -1,6 +1,7 @@
 use bevy::math::prelude::*;
 use bevy::prelude::*;
 
+/// Bounding box of a unit, in the local space of the unit.
 #[derive(Debug, Component)]
 pub struct Region {
     pub rect: Rect,
@@ -18,4 +19,205 @@ impl Region {
     pub fn new(rect: Rect) -> Self {
         Region { rect }
     }
+    /// World positions of the corners of the region of a unit placed at `gt`, counterclockwise.
+    pub fn world_corners(&self, gt: &GlobalTransform) -> [Vec2; 4] {
+        let Rect { min, max } = self.rect;
+        [min, Vec2::new(max.x, min.y), max, Vec2::new(min.x, max.y)]
+            .map(|corner| gt.transform_point(corner.extend(0.0)).truncate())
+    }
+    /// Axis-aligned world bounding box of the region of a unit placed at `gt`.
+    pub fn world_rect(&self, gt: &GlobalTransform) -> Rect {
+        let corners = self.world_corners(gt);
+        corners[1..]
+            .iter()
+            .fold(Rect::from_corners(corners[0], corners[0]), |rect, &p| {
+                rect.union_point(p)
+            })
+    }
+}
+
+/// World position in the local space of an entity placed at `gt`.
+pub fn to_local(gt: &GlobalTransform, point: Vec2) -> Vec2 {
+    gt.affine()
+        .inverse()
+        .transform_point3(point.extend(0.0))
+        .truncate()
+}
+
+/// World lengths of one local unit along the local x and y axes.
+pub fn local_axis_scales(gt: &GlobalTransform) -> Vec2 {
+    let matrix = gt.affine().matrix3;
+    Vec2::new(
+        matrix.x_axis.truncate().length(),
+        matrix.y_axis.truncate().length(),
+    )
+}
+
+/// World length of one local unit, for converting radii and tolerances to local space.
+///
+/// Always positive, a mirrored unit is as large as the unit it mirrors. For a unit scaled more
+/// along one axis this is the longer length, a local radius then reaches no further than the
+/// world radius in any direction.
+pub fn local_scale(gt: &GlobalTransform) -> f32 {
+    local_axis_scales(gt).max_element()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_PI_2;
+
+    use super::*;
+    use crate::{
+        tools::brush::StrokeStyle,
+        unit::stroke::{PointMeasurement, PressureSettings, Stroke, StrokeGroup},
+    };
+
+    const EPSILON: f32 = 1e-4;
+
+    /// Unit scaled by 2, turned a quarter counterclockwise and moved to (10, 20).
+    fn unit_gt() -> GlobalTransform {
+        GlobalTransform::from(
+            Transform::from_xyz(10.0, 20.0, 1.0)
+                .with_rotation(Quat::from_rotation_z(FRAC_PI_2))
+                .with_scale(Vec3::splat(2.0)),
+        )
+    }
+
+    /// The same unit on a board turned a quarter clockwise and moved to (-100, 50).
+    fn board_unit_gt() -> GlobalTransform {
+        let board = GlobalTransform::from(
+            Transform::from_xyz(-100.0, 50.0, 0.0).with_rotation(Quat::from_rotation_z(-FRAC_PI_2)),
+        );
+        board.mul_transform(unit_gt().compute_transform())
+    }
+
+    fn assert_rect_eq(a: Rect, b: Rect) {
+        assert!(
+            a.min.abs_diff_eq(b.min, EPSILON) && a.max.abs_diff_eq(b.max, EPSILON),
+            "{a:?} != {b:?}"
+        );
+    }
+
+    /// Horizontal line from (0, 0) to (10, 0), 2 units wide.
+    fn line_group() -> StrokeGroup {
+        let mut group = StrokeGroup::new();
+        group.strokes.push(Stroke {
+            style: StrokeStyle {
+                width: 2.0,
+                ..Default::default()
+            },
+            measurements: vec![
+                PointMeasurement::new_point(Vec2::ZERO),
+                PointMeasurement::new_point(Vec2::new(10.0, 0.0)),
+            ],
+            curve: Vec::new(),
+        });
+        group
+    }
+
+    /// Whether a world circle touches the ink, the way the picker tests it.
+    fn hits(group: &StrokeGroup, gt: &GlobalTransform, point: Vec2, radius: f32) -> bool {
+        let pressure = PressureSettings::default();
+        group.hit(to_local(gt, point), radius / local_scale(gt), &pressure)
+    }
+
+    #[test]
+    fn world_rect_of_rotated_and_scaled_unit() {
+        let region = Region::new(Rect::new(0.0, 0.0, 4.0, 1.0));
+        // local x goes to world y, local y to world -x, both doubled
+        assert_rect_eq(
+            region.world_rect(&unit_gt()),
+            Rect::new(8.0, 20.0, 10.0, 28.0),
+        );
+    }
+
+    #[test]
+    fn world_rect_on_rotated_and_moved_board() {
+        let region = Region::new(Rect::new(0.0, 0.0, 4.0, 1.0));
+        // the board turns the unit back, its origin is (10, 20) turned clockwise around the board
+        assert_rect_eq(
+            region.world_rect(&board_unit_gt()),
+            Rect::new(-80.0, 40.0, -72.0, 42.0),
+        );
+    }
+
+    #[test]
+    fn to_local_inverts_the_transform() {
+        for gt in [unit_gt(), board_unit_gt()] {
+            let local = Vec2::new(3.0, -7.0);
+            let world = gt.transform_point(local.extend(0.0)).truncate();
+            assert!(to_local(&gt, world).abs_diff_eq(local, EPSILON));
+        }
+        assert!(to_local(&unit_gt(), Vec2::new(10.0, 22.0)).abs_diff_eq(Vec2::X, EPSILON));
+    }
+
+    #[test]
+    fn local_scale_composes_parent_scales() {
+        assert!((local_scale(&unit_gt()) - 2.0).abs() < EPSILON);
+        assert!((local_scale(&board_unit_gt()) - 2.0).abs() < EPSILON);
+    }
+
+    #[test]
+    fn mirrored_unit_keeps_a_positive_scale() {
+        // dragged past the pivot, local x points to world -x
+        let gt = GlobalTransform::from(
+            Transform::from_xyz(10.0, 20.0, 1.0).with_scale(Vec3::new(-2.0, 2.0, 1.0)),
+        );
+        assert!((local_scale(&gt) - 2.0).abs() < EPSILON);
+        assert!(
+            (local_scale(&unit_gt().mul_transform(gt.compute_transform())) - 4.0).abs() < EPSILON
+        );
+        // the line runs from (10, 20) to (-10, 20) and is 4 world units wide
+        let group = line_group();
+        assert!(hits(&group, &gt, Vec2::new(0.0, 22.5), 1.0));
+        assert!(!hits(&group, &gt, Vec2::new(0.0, 23.5), 1.0));
+        assert!(!hits(&group, &gt, Vec2::new(15.0, 20.0), 1.0));
+    }
+
+    #[test]
+    fn stretched_unit_hits_within_the_radius() {
+        // stretched 4 times along local y, then turned a quarter: local y goes to world -x
+        let gt = GlobalTransform::from(
+            Transform::from_rotation(Quat::from_rotation_z(FRAC_PI_2))
+                .with_scale(Vec3::new(1.0, 4.0, 1.0)),
+        );
+        assert!(local_axis_scales(&gt).abs_diff_eq(Vec2::new(1.0, 4.0), EPSILON));
+        assert!((local_scale(&gt) - 4.0).abs() < EPSILON);
+        let mut group = StrokeGroup::new();
+        group.strokes.push(Stroke {
+            style: StrokeStyle {
+                width: 1.0,
+                ..Default::default()
+            },
+            // a dot at the origin, 4 world units wide along world x and 1 along world y
+            measurements: vec![PointMeasurement::new_point(Vec2::ZERO)],
+            curve: Vec::new(),
+        });
+        assert!(hits(&group, &gt, Vec2::new(2.5, 0.0), 1.0));
+        assert!(!hits(&group, &gt, Vec2::new(0.0, 2.0), 1.0));
+        assert!(!hits(&group, &gt, Vec2::new(3.5, 0.0), 1.0));
+    }
+
+    #[test]
+    fn hit_on_rotated_and_scaled_unit() {
+        let group = line_group();
+        let gt = unit_gt();
+        // the line runs from (10, 20) to (10, 40) and is 4 world units wide
+        assert!(hits(&group, &gt, Vec2::new(10.0, 30.0), 0.5));
+        assert!(hits(&group, &gt, Vec2::new(12.5, 30.0), 1.0));
+        assert!(!hits(&group, &gt, Vec2::new(13.5, 30.0), 1.0));
+        assert!(!hits(&group, &gt, Vec2::new(30.0, 20.0), 1.0));
+    }
+
+    #[test]
+    fn hit_on_rotated_and_moved_board() {
+        let group = line_group();
+        let gt = board_unit_gt();
+        // the line runs from (-80, 40) to (-60, 40)
+        assert!(hits(&group, &gt, Vec2::new(-70.0, 40.0), 0.5));
+        assert!(hits(&group, &gt, Vec2::new(-70.0, 42.5), 1.0));
+        assert!(!hits(&group, &gt, Vec2::new(-70.0, 43.5), 1.0));
+        // where the line would be without the board
+        assert!(!hits(&group, &gt, Vec2::new(10.0, 30.0), 1.0));
+    }
 }
@@ -0,0 +1,59 @@
+//! Restyling the ink of the selection.
+use bevy::prelude::*;
+
+use crate::{
+    tools::{Tool, ToolBox},
+    unit::{
+        group::UnitTree,
+        stroke::{PressureSettings, StrokeGroup},
+    },
+};
+
+use super::region::Region;
+
+/// `Ctrl` + `B` gives the selected units and strokes the style of the current brush preset.
+/// Groups are restyled as one, down to every stroke inside them.
+pub fn restyle_system(
+    tool_box: Res<ToolBox>,
+    kbd_input: Res<ButtonInput<KeyCode>>,
+    pressure_settings: Res<PressureSettings>,
+    unit_tree: UnitTree,
+    mut q_group: Query<(&mut StrokeGroup, &mut Region)>,
+) {
+    let (Some(Tool::Picker(picker)), Some(brush)) = (tool_box.current_tool(), tool_box.brush())
+    else {
+        return;
+    };
+    if !kbd_input.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight])
+        || !kbd_input.just_pressed(KeyCode::KeyB)
+    {
+        return;
+    }
+    let style = brush.style();
+    let mut restyled = 0;
+    for leaf in picker
+        .selected
+        .iter()
+        .flat_map(|&entity| unit_tree.leaves(entity))
+    {
+        let Ok((mut stroke_group, mut region)) = q_group.get_mut(leaf) else {
+            continue;
+        };
+        for stroke in stroke_group.strokes.iter_mut() {
+            stroke.style = style;
+            restyled += 1;
+        }
+        region.rect = stroke_group.bounds(&pressure_settings);
+    }
+    for &(entity, index) in &picker.selected_strokes {
+        let Ok((mut stroke_group, mut region)) = q_group.get_mut(entity) else {
+            continue;
+        };
+        if let Some(stroke) = stroke_group.strokes.get_mut(index) {
+            stroke.style = style;
+            restyled += 1;
+        }
+        region.rect = stroke_group.bounds(&pressure_settings);
+    }
+    info!("restyled {restyled} strokes as {style:?}");
+}
@@ -0,0 +1,315 @@
+//! Moving, scaling and rotating the selection, and undoing and redoing it.
+use bevy::{
+    math::{Affine2, Affine3A, Vec3A},
+    prelude::*,
+    window::PrimaryWindow,
+};
+use std::f32::consts::PI;
+
+use crate::{
+    camera::Global2DCamera,
+    tools::{Tool, ToolBox},
+    unit::{
+        group::UnitTree,
+        stroke::{PressureSettings, StrokeGroup},
+    },
+};
+
+use super::{
+    highlight::{box_corners, rotate_handle, HANDLE_SIZE},
+    region::Region,
+    PickerDrag, SelectionOp, PICK_RADIUS,
+};
+
+/// Rotations snap to multiples of this angle while `Shift` is held.
+const ROTATE_SNAP: f32 = PI / 12.0;
+/// Scaling by less than this is clamped, to keep transforms invertible.
+const MIN_SCALE: f32 = 0.01;
+
+/// What dragging the selection does.
+#[derive(Debug, Clone, Copy)]
+pub enum TransformKind {
+    Move,
+    /// Scale away from `pivot`, following the selection box corner at `corner`.
+    Scale {
+        pivot: Vec2,
+        corner: Vec2,
+    },
+    /// Rotate around `pivot`.
+    Rotate {
+        pivot: Vec2,
+    },
+}
+
+/// One finished move, scale or rotation of the selection, the unit of undo and sync.
+#[derive(Debug, Clone, Event)]
+pub struct TransformOp {
+    /// Every changed unit with its transform before and after the operation.
+    pub units: Vec<(Entity, Transform, Transform)>,
+    /// Stroke groups whose points were transformed in their local space to keep line widths.
+    pub points: Vec<(Entity, Affine2)>,
+}
+
+/// Undo and redo stacks of transform operations.
+#[derive(Debug, Default, Resource)]
+pub struct TransformHistory {
+    undo: Vec<TransformOp>,
+    redo: Vec<TransformOp>,
+}
+
+/// The handle of the selection box under the cursor, for a camera at `scale`.
+pub fn grabbed_handle(bounds: Rect, position: Vec2, scale: f32) -> Option<TransformKind> {
+    let reach = HANDLE_SIZE * scale;
+    if rotate_handle(bounds, scale).distance(position) <= reach {
+        return Some(TransformKind::Rotate {
+            pivot: bounds.center(),
+        });
+    }
+    let corners = box_corners(bounds);
+    corners
+        .iter()
+        .position(|corner| (*corner - position).abs().max_element() <= reach)
+        .map(|i| TransformKind::Scale {
+            pivot: corners[(i + 2) % 4],
+            corner: corners[i],
+        })
+}
+
+/// World space transform of a drag from `origin` to `position`.
+fn drag_affine(kind: TransformKind, origin: Vec2, position: Vec2, shift: bool) -> Affine2 {
+    match kind {
+        TransformKind::Move => Affine2::from_translation(position - origin),
+        TransformKind::Scale { pivot, corner } => {
+            let span = corner - pivot;
+            let stretched = position - pivot;
+            let mut scale = Vec2::select(
+                span.abs().cmpgt(Vec2::splat(f32::EPSILON)),
+                stretched / span,
+                Vec2::ONE,
+            );
+            if shift {
+                // keep the aspect ratio
+                let uniform = scale.x.abs().max(scale.y.abs());
+                scale = Vec2::new(uniform.copysign(scale.x), uniform.copysign(scale.y));
+            }
+            let scale = Vec2::new(
+                scale.x.abs().max(MIN_SCALE).copysign(scale.x),
+                scale.y.abs().max(MIN_SCALE).copysign(scale.y),
+            );
+            Affine2::from_translation(pivot)
+                * Affine2::from_scale(scale)
+                * Affine2::from_translation(-pivot)
+        }
+        TransformKind::Rotate { pivot } => {
+            let mut angle = (origin - pivot).angle_between(position - pivot);
+            if angle.is_nan() {
+                angle = 0.0;
+            }
+            if shift {
+                angle = (angle / ROTATE_SNAP).round() * ROTATE_SNAP;
+            }
+            Affine2::from_translation(pivot)
+                * Affine2::from_angle(angle)
+                * Affine2::from_translation(-pivot)
+        }
+    }
+}
+
+fn to_affine3(affine: Affine2) -> Affine3A {
+    Affine3A::from_cols(
+        affine.matrix2.x_axis.extend(0.0).into(),
+        affine.matrix2.y_axis.extend(0.0).into(),
+        Vec3A::Z,
+        affine.translation.extend(0.0).into(),
+    )
+}
+
+/// Whether the axes of a unit placed at `affine` are turned away from the world axes, a
+/// transform cannot hold the shear that scaling it along the world axes gives.
+fn turned(affine: Affine3A) -> bool {
+    let x_axis = affine.x_axis.truncate().normalize_or_zero();
+    x_axis.x.abs().min(x_axis.y.abs()) > 1e-4
+}
+
+pub fn to_affine2(affine: Affine3A) -> Affine2 {
+    Affine2::from_cols(
+        affine.x_axis.truncate(),
+        affine.y_axis.truncate(),
+        affine.translation.truncate(),
+    )
+}
+
+/// Drag the selection with the left mouse button, `Escape` puts it back.
+///
+/// The transforms change while dragging, a [`TransformOp`] is sent once the button is released.
+/// `Shift` keeps the aspect ratio while scaling and snaps rotations. Selections with turned
+/// units, or groups holding turned units, always keep their aspect ratio.
+pub fn transform_selection_system(
+    mut tool_box: ResMut<ToolBox>,
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    kbd_input: Res<ButtonInput<KeyCode>>,
+    pressure_settings: Res<PressureSettings>,
+    mut transform_ops: EventWriter<TransformOp>,
+    q_window: Query<&Window, With<PrimaryWindow>>,
+    q_camera: Query<(&Camera, &GlobalTransform, &OrthographicProjection), With<Global2DCamera>>,
+    unit_tree: UnitTree,
+    q_parent: Query<&Parent>,
+    q_gt: Query<&GlobalTransform>,
+    mut q_transform: Query<&mut Transform>,
+    mut q_group: Query<(&mut StrokeGroup, &mut Region)>,
+) {
+    let Some(Tool::Picker(picker)) = tool_box.current_tool_mut() else {
+        return;
+    };
+    let Some(PickerDrag::Transform {
+        kind,
+        origin,
+        clicked,
+        start,
+    }) = &picker.drag
+    else {
+        return;
+    };
+    let (camera, camera_gt, projection) = q_camera.single();
+    let Some(mouse_position) = q_window
+        .single()
+        .cursor_position()
+        .and_then(|p| camera.viewport_to_world_2d(camera_gt, p))
+    else {
+        return;
+    };
+    // a drag shorter than the pick radius is a click
+    let moved = origin.distance(mouse_position) > PICK_RADIUS * projection.scale;
+    let cancel = kbd_input.just_pressed(KeyCode::Escape);
+    let parent_affine = |entity: Entity| {
+        q_parent
+            .get(entity)
+            .and_then(|parent| q_gt.get(parent.get()))
+            .map_or(Affine3A::IDENTITY, GlobalTransform::affine)
+    };
+    // a unit inside a group can be turned even when the group is not
+    let uniform = matches!(kind, TransformKind::Scale { .. })
+        && start.iter().any(|&(entity, before)| {
+            let unit = parent_affine(entity) * before.compute_affine();
+            let Ok(unit_gt) = q_gt.get(entity) else {
+                return turned(unit);
+            };
+            let to_unit = unit * unit_gt.affine().inverse();
+            unit_tree
+                .descendants(entity)
+                .into_iter()
+                .filter_map(|inner| q_gt.get(inner).ok())
+                .any(|inner_gt| turned(to_unit * inner_gt.affine()))
+        });
+    let shift = uniform || kbd_input.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+    let world = to_affine3(drag_affine(*kind, *origin, mouse_position, shift));
+    for &(entity, before) in start {
+        let Ok(mut transform) = q_transform.get_mut(entity) else {
+            continue;
+        };
+        *transform = before;
+        if moved && !cancel {
+            let parent = parent_affine(entity);
+            let local = parent.inverse() * world * parent * before.compute_affine();
+            *transform = Transform::from_matrix(local.into());
+        }
+    }
+    if mouse_input.pressed(MouseButton::Left) && !cancel {
+        return;
+    }
+    let (kind, clicked) = (*kind, *clicked);
+    let Some(PickerDrag::Transform { start, .. }) = picker.drag.take() else {
+        return;
+    };
+    if cancel {
+        return;
+    }
+    if !moved {
+        // a click on a selected unit selects only that unit
+        if let Some(entity) = clicked {
+            picker.pick_units(SelectionOp::Replace, [entity]);
+        }
+        return;
+    }
+    let mut points = Vec::new();
+    if matches!(kind, TransformKind::Scale { .. }) && picker.keep_stroke_width {
+        // put the transforms back and move the ink instead, of every stroke group in groups too
+        for &(entity, before) in &start {
+            let Ok(mut transform) = q_transform.get_mut(entity) else {
+                continue;
+            };
+            *transform = before;
+            let unit = parent_affine(entity) * before.compute_affine();
+            let Ok(unit_gt) = q_gt.get(entity) else {
+                continue;
+            };
+            for leaf in unit_tree.leaves(entity) {
+                let (Ok((mut stroke_group, mut region)), Ok(leaf_gt)) =
+                    (q_group.get_mut(leaf), q_gt.get(leaf))
+                else {
+                    continue;
+                };
+                // where the leaf is in the unit does not change while dragging
+                let leaf_affine = unit * unit_gt.affine().inverse() * leaf_gt.affine();
+                let local = to_affine2(leaf_affine.inverse() * world * leaf_affine);
+                stroke_group.transform_points(local);
+                region.rect = stroke_group.bounds(&pressure_settings);
+                points.push((leaf, local));
+            }
+        }
+    }
+    let units = start
+        .into_iter()
+        .filter_map(|(entity, before)| {
+            let after = *q_transform.get(entity).ok()?;
+            Some((entity, before, after))
+        })
+        .collect();
+    transform_ops.send(TransformOp { units, points });
+}
+
+/// Keep finished transform operations, `Ctrl` + `Z` undoes and `Ctrl` + `Y` or
+/// `Ctrl` + `Shift` + `Z` redoes them.
+pub fn transform_history_system(
+    mut history: ResMut<TransformHistory>,
+    kbd_input: Res<ButtonInput<KeyCode>>,
+    pressure_settings: Res<PressureSettings>,
+    mut transform_ops: EventReader<TransformOp>,
+    mut q_transform: Query<&mut Transform>,
+    mut q_group: Query<(&mut StrokeGroup, &mut Region)>,
+) {
+    for op in transform_ops.read() {
+        history.undo.push(op.clone());
+        history.redo.clear();
+    }
+    if !kbd_input.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]) {
+        return;
+    }
+    let shift = kbd_input.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+    let undo = kbd_input.just_pressed(KeyCode::KeyZ) && !shift;
+    let redo =
+        kbd_input.just_pressed(KeyCode::KeyY) || (shift && kbd_input.just_pressed(KeyCode::KeyZ));
+    let history = &mut *history;
+    let (from, to, undoing) = if undo {
+        (&mut history.undo, &mut history.redo, true)
+    } else if redo {
+        (&mut history.redo, &mut history.undo, false)
+    } else {
+        return;
+    };
+    let Some(op) = from.pop() else {
+        return;
+    };
+    for &(entity, before, after) in &op.units {
+        if let Ok(mut transform) = q_transform.get_mut(entity) {
+            *transform = if undoing { before } else { after };
+        }
+    }
+    for &(entity, affine) in &op.points {
+        if let Ok((mut stroke_group, mut region)) = q_group.get_mut(entity) {
+            stroke_group.transform_points(if undoing { affine.inverse() } else { affine });
+            region.rect = stroke_group.bounds(&pressure_settings);
+        }
+    }
+    to.push(op);
+}
@@ -0,0 +1,59 @@
+//! Hides units outside of the view.
+use bevy::{prelude::*, utils::HashSet};
+
+use crate::camera::Global2DCamera;
+
+use super::{index::UnitIndex, Unit};
+
+/// Units just outside the view are kept visible, in screen pixels.
+const CULL_MARGIN: f32 = 64.0;
+
+/// Units whose world bounds intersect the view.
+#[derive(Debug, Default, Resource)]
+pub struct VisibleUnits(HashSet<Entity>);
+
+impl std::ops::Deref for VisibleUnits {
+    type Target = HashSet<Entity>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+/// Hide units that left the view and show units that entered it.
+///
+/// Only units that cross the edge of the view are touched, and units spawned outside of it
+/// once they are indexed.
+pub fn cull_units_system(
+    mut visible_units: ResMut<VisibleUnits>,
+    mut unit_index: ResMut<UnitIndex>,
+    q_camera: Query<(&GlobalTransform, &OrthographicProjection), With<Global2DCamera>>,
+    mut q_unit: Query<&mut Visibility, With<Unit>>,
+) {
+    let (camera_gt, projection) = q_camera.single();
+    let center = camera_gt.translation().truncate();
+    let view = Rect {
+        min: projection.area.min + center,
+        max: projection.area.max + center,
+    }
+    .inset(CULL_MARGIN * projection.scale);
+    let visible = unit_index.query_rect(view).collect::<HashSet<_>>();
+    for entity in unit_index.drain_added() {
+        if !visible.contains(&entity) {
+            if let Ok(mut visibility) = q_unit.get_mut(entity) {
+                *visibility = Visibility::Hidden;
+            }
+        }
+    }
+    for &entity in visible_units.difference(&visible) {
+        if let Ok(mut visibility) = q_unit.get_mut(entity) {
+            *visibility = Visibility::Hidden;
+        }
+    }
+    for &entity in visible.difference(&visible_units) {
+        if let Ok(mut visibility) = q_unit.get_mut(entity) {
+            *visibility = Visibility::Inherited;
+        }
+    }
+    visible_units.0 = visible;
+}
@@ -0,0 +1,121 @@
+//! Explicit groups of units, picked, moved and copied as one unit.
+use bevy::{ecs::system::SystemParam, prelude::*};
+
+use crate::tools::picker::region::Region;
+
+use super::Unit;
+
+/// Unit whose children are units.
+#[derive(Component)]
+pub struct UnitGroup;
+
+/// Components of an empty group placed at `transform` relative to its parent, its region
+/// follows its children.
+pub fn unit_group_bundle(transform: Transform) -> impl Bundle {
+    (
+        UnitGroup,
+        Unit { layer: 0 },
+        Region::from_point(Vec2::ZERO),
+        SpatialBundle {
+            transform,
+            ..Default::default()
+        },
+    )
+}
+
+/// Walks the hierarchy of units and groups.
+#[derive(SystemParam)]
+pub struct UnitTree<'w, 's> {
+    q_parent: Query<'w, 's, &'static Parent>,
+    q_children: Query<'w, 's, &'static Children>,
+    q_unit: Query<'w, 's, Has<UnitGroup>, With<Unit>>,
+}
+
+impl UnitTree<'_, '_> {
+    pub fn is_unit(&self, entity: Entity) -> bool {
+        self.q_unit.contains(entity)
+    }
+    pub fn is_group(&self, entity: Entity) -> bool {
+        self.q_unit.get(entity).unwrap_or(false)
+    }
+    /// The outermost group around a unit, or the unit itself when it is not grouped.
+    pub fn outermost(&self, entity: Entity) -> Entity {
+        let mut outermost = entity;
+        let mut current = entity;
+        while let Ok(parent) = self.q_parent.get(current) {
+            current = parent.get();
+            if self.is_group(current) {
+                outermost = current;
+            }
+        }
+        outermost
+    }
+    /// Units directly inside a group.
+    pub fn members(&self, group: Entity) -> Vec<Entity> {
+        self.q_children
+            .get(group)
+            .map(|children| {
+                children
+                    .iter()
+                    .copied()
+                    .filter(|&child| self.is_unit(child))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+    /// The unit and every unit inside it at any depth, inner groups included.
+    pub fn descendants(&self, entity: Entity) -> Vec<Entity> {
+        let mut units = vec![entity];
+        for member in self.members(entity) {
+            units.extend(self.descendants(member));
+        }
+        units
+    }
+    /// Units that are not groups, inside a group at any depth, or the unit itself.
+    pub fn leaves(&self, entity: Entity) -> Vec<Entity> {
+        if !self.is_group(entity) {
+            return vec![entity];
+        }
+        self.members(entity)
+            .into_iter()
+            .flat_map(|member| self.leaves(member))
+            .collect()
+    }
+}
+
+/// Fit the region of every group around its members, inner groups first.
+/// Groups left without members are despawned.
+pub fn group_region_system(
+    mut commands: Commands,
+    unit_tree: UnitTree,
+    q_group: Query<Entity, With<UnitGroup>>,
+    mut q_region: Query<(&mut Region, &Transform)>,
+) {
+    let depth = |mut entity: Entity| {
+        let mut depth = 0;
+        while let Ok(parent) = unit_tree.q_parent.get(entity) {
+            entity = parent.get();
+            depth += 1;
+        }
+        depth
+    };
+    let mut groups = q_group.iter().collect::<Vec<_>>();
+    groups.sort_by_key(|&group| std::cmp::Reverse(depth(group)));
+    for group in groups {
+        let rect = unit_tree
+            .members(group)
+            .into_iter()
+            .filter_map(|member| q_region.get(member).ok())
+            .map(|(region, transform)| region.world_rect(&GlobalTransform::from(*transform)))
+            .reduce(|a, b| a.union(b));
+        let Some(rect) = rect else {
+            commands.entity(group).despawn_recursive();
+            continue;
+        };
+        if let Ok((mut region, _)) = q_region.get_mut(group) {
+            if region.rect != rect {
+                region.rect = rect;
+            }
+        }
+    }
+}
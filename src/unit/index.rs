@@ -0,0 +1,78 @@
+//! Spatial index of the world bounds of every unit.
+use bevy::{prelude::*, utils::HashMap};
+use rstar::{
+    primitives::{GeomWithData, Rectangle},
+    RTree, AABB,
+};
+
+use crate::tools::picker::region::Region;
+
+use super::Unit;
+
+type IndexedRect = GeomWithData<Rectangle<[f32; 2]>, Entity>;
+
+#[derive(Default, Resource)]
+pub struct UnitIndex {
+    tree: RTree<IndexedRect>,
+    /// World bounds each unit was indexed with, needed to find it again in the tree.
+    bounds: HashMap<Entity, Rect>,
+    /// Units indexed for the first time since the last call to [`UnitIndex::drain_added`].
+    added: Vec<Entity>,
+}
+
+impl UnitIndex {
+    pub fn insert(&mut self, entity: Entity, rect: Rect) {
+        match self.bounds.insert(entity, rect) {
+            Some(old) => {
+                self.tree.remove(&indexed(entity, old));
+            }
+            None => self.added.push(entity),
+        }
+        self.tree.insert(indexed(entity, rect));
+    }
+    pub fn remove(&mut self, entity: Entity) {
+        if let Some(rect) = self.bounds.remove(&entity) {
+            self.tree.remove(&indexed(entity, rect));
+        }
+    }
+    pub fn drain_added(&mut self) -> impl Iterator<Item = Entity> + '_ {
+        self.added.drain(..)
+    }
+    /// Units whose world bounds intersect `rect`.
+    pub fn query_rect(&self, rect: Rect) -> impl Iterator<Item = Entity> + '_ {
+        let envelope = AABB::from_corners(rect.min.into(), rect.max.into());
+        self.tree
+            .locate_in_envelope_intersecting(&envelope)
+            .map(|indexed| indexed.data)
+    }
+    /// Units whose world bounds are within `radius` of `point`.
+    pub fn query_point(&self, point: Vec2, radius: f32) -> impl Iterator<Item = Entity> + '_ {
+        self.query_rect(Rect::from_center_half_size(point, Vec2::splat(radius)))
+    }
+}
+
+fn indexed(entity: Entity, rect: Rect) -> IndexedRect {
+    GeomWithData::new(
+        Rectangle::from_corners(rect.min.into(), rect.max.into()),
+        entity,
+    )
+}
+
+/// Re-index units that changed shape or moved, and forget despawned ones.
+///
+/// Runs after transform propagation, queries see the state of the previous frame.
+pub fn update_unit_index_system(
+    mut index: ResMut<UnitIndex>,
+    mut removed_units: RemovedComponents<Unit>,
+    q_unit: Query<
+        (Entity, &Region, &GlobalTransform),
+        (With<Unit>, Or<(Changed<Region>, Changed<GlobalTransform>)>),
+    >,
+) {
+    for entity in removed_units.read() {
+        index.remove(entity);
+    }
+    for (entity, region, gt) in q_unit.iter() {
+        index.insert(entity, region.world_rect(gt));
+    }
+}
@@ -1,24 +1,58 @@
-use bevy::prelude::*;
-mod stroke;
+use bevy::{prelude::*, transform::TransformSystem};
+pub mod cull;
+pub mod group;
+pub mod index;
+pub mod stroke;
 #[derive(Component)]
 pub struct Unit {
     layer: u32,
 }
 
+impl Unit {
+    /// Units on higher layers are drawn and picked above lower ones.
+    pub fn layer(&self) -> u32 {
+        self.layer
+    }
+}
+
 #[derive(Component)]
 pub struct Active;
 #[derive(Component)]
 pub struct Rendered;
 
-
 #[derive(Component)]
 pub struct Layer(u32);
 pub struct UnitPlugin;
 
 impl Plugin for UnitPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update, stroke::stroke_record_system)
-            .add_systems(Update, stroke::render_strokes_system);
+        app.add_plugins(stroke::material::StrokeMaterialPlugin)
+            .init_resource::<stroke::PressureSettings>()
+            .init_resource::<stroke::smooth::SmoothingSettings>()
+            .init_resource::<stroke::simplify::SimplifySettings>()
+            .init_resource::<stroke::replay::Replay>()
+            .init_resource::<stroke::lod::LodSettings>()
+            .init_resource::<stroke::raster::RasterCacheSettings>()
+            .init_resource::<index::UnitIndex>()
+            .init_resource::<cull::VisibleUnits>()
+            .add_systems(
+                Update,
+                (
+                    stroke::stroke_record_system,
+                    cull::cull_units_system,
+                    stroke::lod::lod_system,
+                    stroke::invalidate_strokes_system,
+                    stroke::render_strokes_system,
+                    stroke::raster::raster_cache_system,
+                )
+                    .chain(),
+            )
+            .add_systems(Update, stroke::replay::replay_system)
+            .add_systems(Update, group::group_region_system)
+            .add_systems(
+                PostUpdate,
+                index::update_unit_index_system.after(TransformSystem::TransformPropagate),
+            );
     }
 }
-// Board -> Unit
\ No newline at end of file
+// Board -> Unit
@@ -0,0 +1,201 @@
+//! Cutting pieces out of strokes.
+use bevy::prelude::*;
+
+use super::{PointMeasurement, PressureSettings, Stroke};
+
+impl Stroke {
+    /// The pieces of the stroke left after removing the ink under circles of `radius` around
+    /// `centers`. Pressure and time are interpolated where the stroke is cut.
+    ///
+    /// The fitted curve is cut, it is what is drawn and hit. The raw samples recorded between
+    /// the ends of a piece come along with it, so pieces keep their shape and their timing.
+    pub fn cut(&self, centers: &[Vec2], radius: f32, pressure: &PressureSettings) -> Vec<Stroke> {
+        let reach =
+            |m: &PointMeasurement| radius + pressure.stroke_width(&self.style, m.press) * 0.5;
+        let mut pieces = vec![self.curve().to_vec()];
+        for &center in centers {
+            pieces = pieces
+                .iter()
+                .flat_map(|piece| cut_circle(piece, center, &reach))
+                .collect();
+        }
+        pieces
+            .into_iter()
+            .map(|curve| {
+                if self.curve.is_empty() {
+                    return Stroke {
+                        style: self.style,
+                        measurements: curve,
+                        curve: Vec::new(),
+                    };
+                }
+                let (first, last) = (curve[0], curve[curve.len() - 1]);
+                let mut measurements = vec![first];
+                measurements.extend(
+                    self.measurements
+                        .iter()
+                        .filter(|m| m.time > first.time && m.time < last.time),
+                );
+                if curve.len() > 1 {
+                    measurements.push(last);
+                }
+                Stroke {
+                    style: self.style,
+                    measurements,
+                    curve,
+                }
+            })
+            .collect()
+    }
+}
+
+/// Split a polyline into the parts outside a circle, `reach` is the radius at a sample.
+fn cut_circle(
+    samples: &[PointMeasurement],
+    center: Vec2,
+    reach: &impl Fn(&PointMeasurement) -> f32,
+) -> Vec<Vec<PointMeasurement>> {
+    let mut pieces = Vec::new();
+    let mut current = Vec::new();
+    match samples {
+        [] => return pieces,
+        [m] => {
+            if m.point.distance(center) > reach(m) {
+                pieces.push(vec![*m]);
+            }
+            return pieces;
+        }
+        [first, ..] => {
+            if first.point.distance(center) > reach(first) {
+                current.push(*first);
+            }
+        }
+    }
+    for w in samples.windows(2) {
+        let (a, b) = (&w[0], &w[1]);
+        match inside_interval(a.point, b.point, center, reach(a).max(reach(b))) {
+            None => current.push(*b),
+            Some((enter, leave)) => {
+                if enter > 0.0 {
+                    current.push(a.lerp(b, enter));
+                }
+                if !current.is_empty() {
+                    pieces.push(std::mem::take(&mut current));
+                }
+                if leave < 1.0 {
+                    current.push(a.lerp(b, leave));
+                    current.push(*b);
+                }
+            }
+        }
+    }
+    if !current.is_empty() {
+        pieces.push(current);
+    }
+    pieces
+}
+
+/// Part of the segment `a`-`b` inside the circle, as parameters along the segment in `0.0..=1.0`.
+fn inside_interval(a: Vec2, b: Vec2, center: Vec2, radius: f32) -> Option<(f32, f32)> {
+    let d = b - a;
+    let f = a - center;
+    let (qa, qb, qc) = (d.dot(d), 2.0 * f.dot(d), f.dot(f) - radius * radius);
+    if qa <= f32::EPSILON {
+        return (qc <= 0.0).then_some((0.0, 1.0));
+    }
+    let discriminant = qb * qb - 4.0 * qa * qc;
+    if discriminant < 0.0 {
+        return None;
+    }
+    let root = discriminant.sqrt();
+    let enter = ((-qb - root) / (2.0 * qa)).max(0.0);
+    let leave = ((-qb + root) / (2.0 * qa)).min(1.0);
+    (enter < leave).then_some((enter, leave))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{tools::brush::StrokeStyle, unit::stroke::smooth::SmoothingSettings};
+
+    /// A V with its tip at (10, 20), one sample every 10 ms, fitted with the default smoothing.
+    fn v_stroke() -> Stroke {
+        let mut stroke = Stroke::new(StrokeStyle {
+            width: 2.0,
+            ..Default::default()
+        });
+        stroke.measurements = [
+            (0.0, 0.0),
+            (5.0, 10.0),
+            (10.0, 20.0),
+            (15.0, 10.0),
+            (20.0, 0.0),
+        ]
+        .iter()
+        .enumerate()
+        .map(|(i, &(x, y))| PointMeasurement {
+            time: Duration::from_millis(i as u64 * 10),
+            ..PointMeasurement::new_point(Vec2::new(x, y))
+        })
+        .collect();
+        stroke.fit_curve(&SmoothingSettings::default());
+        stroke
+    }
+
+    #[test]
+    fn cutting_the_visible_tip_removes_it() {
+        let stroke = v_stroke();
+        let pressure = PressureSettings::default();
+        // the curve tip is pulled well below the raw one
+        let tip = stroke
+            .curve()
+            .iter()
+            .map(|m| m.point)
+            .max_by(|a, b| a.y.total_cmp(&b.y))
+            .unwrap();
+        assert!(tip.y < 18.0);
+        assert!(stroke.hit(tip, 1.0, &pressure));
+        let pieces = stroke.cut(&[tip], 1.0, &pressure);
+        assert_eq!(pieces.len(), 2);
+        for piece in &pieces {
+            // the cut ends touch the circle
+            assert!(!piece.hit(tip, 0.9, &pressure));
+            // raw samples stay in time order, inside the times of the piece's curve
+            let curve = piece.curve();
+            assert!(piece
+                .measurements
+                .windows(2)
+                .all(|w| w[0].time <= w[1].time));
+            assert_eq!(piece.measurements[0].time, curve[0].time);
+            assert_eq!(
+                piece.measurements.last().unwrap().time,
+                curve.last().unwrap().time
+            );
+        }
+        // the raw samples of each arm come along, the raw tip is cut away
+        let raw = |piece: &Stroke| {
+            piece
+                .measurements
+                .iter()
+                .map(|m| m.point)
+                .collect::<Vec<_>>()
+        };
+        assert!(raw(&pieces[0]).contains(&Vec2::new(5.0, 10.0)));
+        assert!(raw(&pieces[1]).contains(&Vec2::new(15.0, 10.0)));
+        assert!(!pieces
+            .iter()
+            .any(|piece| raw(piece).contains(&Vec2::new(10.0, 20.0))));
+    }
+
+    #[test]
+    fn unfitted_strokes_are_cut_along_their_samples() {
+        let mut stroke = v_stroke();
+        stroke.curve.clear();
+        let pressure = PressureSettings::default();
+        let pieces = stroke.cut(&[Vec2::new(10.0, 20.0)], 1.0, &pressure);
+        assert_eq!(pieces.len(), 2);
+        assert!(pieces.iter().all(|piece| piece.curve.is_empty()));
+    }
+}
@@ -0,0 +1,9 @@
+#import bevy_sprite::mesh2d_vertex_output::VertexOutput
+
+@group(2) @binding(0) var<uniform> color: vec4<f32>;
+
+// The blend state multiplies this with the ink below, see `HighlighterMaterial::specialize`.
+@fragment
+fn fragment(mesh: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(color.rgb * color.a, color.a);
+}
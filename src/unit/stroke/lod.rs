@@ -0,0 +1,100 @@
+//! Coarser stroke meshes for zoomed out views.
+//!
+//! Each zoom level above full detail doubles the camera scale it covers. Visible groups whose
+//! meshes were built for another level are rendered again.
+use bevy::prelude::*;
+
+use crate::{camera::Global2DCamera, unit::cull::VisibleUnits};
+
+use super::{Rendered, StrokeGroup};
+
+#[derive(Debug, Resource)]
+pub struct LodSettings {
+    /// Camera scale up to which strokes are meshed with every point.
+    pub full_detail_scale: f32,
+    /// Simplification tolerance of coarse meshes, in screen pixels.
+    pub tolerance: f32,
+    /// Thinnest line of coarse meshes, in screen pixels.
+    pub min_width: f32,
+    /// Most groups rendered again per frame after the level changed, the others keep their
+    /// meshes until their turn.
+    pub max_updates: usize,
+}
+
+impl Default for LodSettings {
+    fn default() -> Self {
+        Self {
+            full_detail_scale: 1.0,
+            tolerance: 1.0,
+            min_width: 1.0,
+            max_updates: 32,
+        }
+    }
+}
+
+impl LodSettings {
+    /// Level of detail for a camera scale, `0` is full detail.
+    pub fn level(&self, scale: f32) -> u8 {
+        if scale < self.full_detail_scale {
+            0
+        } else {
+            (scale / self.full_detail_scale).log2().floor() as u8 + 1
+        }
+    }
+    /// How to mesh strokes at `level`, for a group whose local units are `local_scale` world
+    /// units.
+    pub fn detail(&self, level: u8, local_scale: f32) -> Detail {
+        if level == 0 {
+            return Detail::FULL;
+        }
+        // the most zoomed out camera scale of the level, in local units
+        let scale = self.full_detail_scale * 2f32.powi(level as i32) / local_scale;
+        Detail {
+            tolerance: self.tolerance * scale,
+            min_width: self.min_width * scale,
+        }
+    }
+}
+
+/// Simplification of a stroke mesh, in the local units of its group.
+#[derive(Debug, Clone, Copy)]
+pub struct Detail {
+    pub tolerance: f32,
+    pub min_width: f32,
+}
+
+impl Detail {
+    pub const FULL: Detail = Detail {
+        tolerance: 0.0,
+        min_width: 0.0,
+    };
+}
+
+/// Level of detail the meshes of a group were built for.
+#[derive(Debug, Component, Clone, Copy, PartialEq, Eq)]
+pub struct Lod(pub u8);
+
+/// Mark visible groups rendered at another level of detail as changed, so they are rendered again.
+///
+/// Only [`LodSettings::max_updates`] groups are marked per frame, so crossing a level spreads
+/// the work over several frames.
+pub fn lod_system(
+    lod_settings: Res<LodSettings>,
+    visible_units: Res<VisibleUnits>,
+    q_camera: Query<&OrthographicProjection, With<Global2DCamera>>,
+    mut q_group: Query<(&mut StrokeGroup, &Lod), With<Rendered>>,
+) {
+    let level = lod_settings.level(q_camera.single().scale);
+    let mut updates = 0;
+    for &entity in visible_units.iter() {
+        if updates >= lod_settings.max_updates {
+            break;
+        }
+        if let Ok((mut stroke_group, lod)) = q_group.get_mut(entity) {
+            if lod.0 != level {
+                stroke_group.set_changed();
+                updates += 1;
+            }
+        }
+    }
+}
@@ -0,0 +1,192 @@
+//! Materials for the different kinds of ink.
+use bevy::{
+    asset::load_internal_asset,
+    ecs::system::SystemParam,
+    prelude::*,
+    render::{
+        mesh::MeshVertexBufferLayout,
+        render_asset::RenderAssetUsages,
+        render_resource::{
+            AsBindGroup, BlendComponent, BlendFactor, BlendOperation, BlendState, Extent3d,
+            RenderPipelineDescriptor, ShaderRef, SpecializedMeshPipelineError, TextureDimension,
+            TextureFormat,
+        },
+        texture::{ImageAddressMode, ImageSampler, ImageSamplerDescriptor},
+    },
+    sprite::{Material2d, Material2dKey, Material2dPlugin, Mesh2dHandle},
+    utils::HashMap,
+};
+
+use crate::tools::brush::{BrushKind, StrokeStyle};
+
+pub const HIGHLIGHTER_SHADER_HANDLE: Handle<Shader> =
+    Handle::weak_from_u128(0x7f3c_42a1_9b5e_4d8c_a2f6_1e0b_93d7_c511);
+
+/// Side length of the pencil grain texture, in pixels.
+const GRAIN_SIZE: u32 = 64;
+
+pub struct StrokeMaterialPlugin;
+
+impl Plugin for StrokeMaterialPlugin {
+    fn build(&self, app: &mut App) {
+        load_internal_asset!(
+            app,
+            HIGHLIGHTER_SHADER_HANDLE,
+            "highlighter.wgsl",
+            Shader::from_wgsl
+        );
+        app.add_plugins(Material2dPlugin::<HighlighterMaterial>::default())
+            .init_resource::<StrokeMaterials>();
+    }
+}
+
+/// Depth of a stroke mesh inside its group, highlighter ink goes under other ink.
+pub fn ink_depth(kind: BrushKind) -> f32 {
+    match kind {
+        BrushKind::Highlighter => 1.5,
+        _ => 2.0,
+    }
+}
+
+/// Whether ink of `style` covers what is below it completely. Pencil ink is never opaque, its
+/// grain lets the paper through.
+pub fn opaque_ink(style: &StrokeStyle) -> bool {
+    style.ink().a() >= 1.0 && !matches!(style.kind, BrushKind::Highlighter | BrushKind::Pencil)
+}
+
+/// Translucent ink that is multiplied with whatever is below it.
+#[derive(Asset, TypePath, AsBindGroup, Debug, Clone)]
+pub struct HighlighterMaterial {
+    #[uniform(0)]
+    pub color: Color,
+}
+
+impl Material2d for HighlighterMaterial {
+    fn fragment_shader() -> ShaderRef {
+        HIGHLIGHTER_SHADER_HANDLE.into()
+    }
+
+    fn specialize(
+        descriptor: &mut RenderPipelineDescriptor,
+        _layout: &MeshVertexBufferLayout,
+        _key: Material2dKey<Self>,
+    ) -> Result<(), SpecializedMeshPipelineError> {
+        let target = descriptor
+            .fragment
+            .as_mut()
+            .and_then(|fragment| fragment.targets.first_mut())
+            .and_then(Option::as_mut);
+        if let Some(target) = target {
+            target.blend = Some(BlendState {
+                color: BlendComponent {
+                    src_factor: BlendFactor::Dst,
+                    dst_factor: BlendFactor::OneMinusSrcAlpha,
+                    operation: BlendOperation::Add,
+                },
+                alpha: BlendComponent::OVER,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Materials shared by stroke meshes, one per kind of ink and color.
+#[derive(Debug, Resource)]
+pub struct StrokeMaterials {
+    grain: Handle<Image>,
+    inks: HashMap<(BrushKind, [u32; 4]), Handle<ColorMaterial>>,
+    highlighters: HashMap<[u32; 4], Handle<HighlighterMaterial>>,
+}
+
+impl FromWorld for StrokeMaterials {
+    fn from_world(world: &mut World) -> Self {
+        let mut images = world.resource_mut::<Assets<Image>>();
+        Self {
+            grain: images.add(grain_image()),
+            inks: HashMap::new(),
+            highlighters: HashMap::new(),
+        }
+    }
+}
+
+/// White texture with a noisy alpha, gives pencil ink its paper grain.
+fn grain_image() -> Image {
+    // xorshift, the grain only has to look random
+    let mut state: u32 = 0x9e37_79b9;
+    let data = (0..GRAIN_SIZE * GRAIN_SIZE)
+        .flat_map(|_| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            [255, 255, 255, 96 + (state % 160) as u8]
+        })
+        .collect();
+    let mut image = Image::new(
+        Extent3d {
+            width: GRAIN_SIZE,
+            height: GRAIN_SIZE,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::RENDER_WORLD,
+    );
+    image.sampler = ImageSampler::Descriptor(ImageSamplerDescriptor {
+        address_mode_u: ImageAddressMode::Repeat,
+        address_mode_v: ImageAddressMode::Repeat,
+        ..ImageSamplerDescriptor::linear()
+    });
+    image
+}
+
+/// Everything needed to give a stroke mesh the material of its style.
+#[derive(SystemParam)]
+pub struct InkMaterials<'w> {
+    stroke_materials: ResMut<'w, StrokeMaterials>,
+    color_materials: ResMut<'w, Assets<ColorMaterial>>,
+    highlighter_materials: ResMut<'w, Assets<HighlighterMaterial>>,
+}
+
+impl InkMaterials<'_> {
+    /// Spawn a stroke mesh drawn with `style`, together with `bundle`.
+    pub fn spawn_mesh(
+        &mut self,
+        commands: &mut Commands,
+        mesh: Handle<Mesh>,
+        style: &StrokeStyle,
+        bundle: impl Bundle,
+    ) -> Entity {
+        let mut entity = commands.spawn((
+            bundle,
+            Mesh2dHandle(mesh),
+            SpatialBundle::from_transform(Transform::from_xyz(0.0, 0.0, ink_depth(style.kind))),
+        ));
+        let color = style.ink();
+        let key = color.as_rgba_f32().map(f32::to_bits);
+        match style.kind {
+            BrushKind::Highlighter => {
+                let material = self
+                    .stroke_materials
+                    .highlighters
+                    .entry(key)
+                    .or_insert_with(|| {
+                        self.highlighter_materials
+                            .add(HighlighterMaterial { color })
+                    });
+                entity.insert(material.clone());
+            }
+            kind => {
+                let texture =
+                    (kind == BrushKind::Pencil).then(|| self.stroke_materials.grain.clone());
+                let material = self
+                    .stroke_materials
+                    .inks
+                    .entry((kind, key))
+                    .or_insert_with(|| self.color_materials.add(ColorMaterial { color, texture }));
+                entity.insert(material.clone());
+            }
+        }
+        entity.id()
+    }
+}
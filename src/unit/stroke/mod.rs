@@ -1,18 +1,42 @@
 use bevy::{
-    input::{mouse::MouseButtonInput, ButtonState},
-    math,
+    input::touch::{ForceTouch, TouchPhase},
+    math::Affine2,
     prelude::*,
+    sprite::Mesh2dHandle,
     window::PrimaryWindow,
 };
+use std::{
+    f32::consts::PI,
+    time::{Duration, Instant},
+};
 
 use crate::{
     board::Board,
     camera::Global2DCamera,
     time::LastUpdate,
-    tools::{picker::region::Region, Tool, ToolBox},
+    tools::{
+        brush::StrokeStyle,
+        picker::region::{self, Region},
+        Tool, ToolBox,
+    },
 };
 
 use super::{Active, Rendered, Unit};
+use lod::{Detail, Lod, LodSettings};
+use material::InkMaterials;
+use raster::{RasterCache, RasterSprite};
+use simplify::SimplifySettings;
+use smooth::SmoothingSettings;
+
+pub mod cut;
+pub mod lod;
+pub mod material;
+pub mod raster;
+pub mod replay;
+pub mod simplify;
+pub mod smooth;
+pub mod snapshot;
+pub mod tessellate;
 
 pub enum DrawingStatus {
     Created,
@@ -20,45 +44,255 @@ pub enum DrawingStatus {
     Finished,
 }
 
-#[derive(Component, Debug, Default)]
+#[derive(Component, Debug, Clone)]
 pub struct StrokeGroup {
     pub strokes: Vec<Stroke>,
     pub active_stroke: Option<Stroke>,
+    /// Measurement times are relative to this instant.
+    pub started_at: Instant,
+}
+
+impl Default for StrokeGroup {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl StrokeGroup {
     pub fn new() -> Self {
-        Self::default()
+        Self {
+            strokes: Vec::new(),
+            active_stroke: None,
+            started_at: Instant::now(),
+        }
+    }
+    /// Bounding box of the inked area of every finished stroke.
+    pub fn bounds(&self, pressure: &PressureSettings) -> Rect {
+        self.strokes
+            .iter()
+            .filter_map(|stroke| stroke.bounds(pressure))
+            .reduce(|a, b| a.union(b))
+            .unwrap_or_default()
+    }
+    /// Whether a circle at `point` touches the ink of any finished stroke.
+    pub fn hit(&self, point: Vec2, radius: f32, pressure: &PressureSettings) -> bool {
+        self.strokes
+            .iter()
+            .any(|stroke| stroke.hit(point, radius, pressure))
+    }
+    /// Move every point of every stroke, line widths stay the same.
+    pub fn transform_points(&mut self, affine: Affine2) {
+        for stroke in self.strokes.iter_mut() {
+            for m in stroke
+                .measurements
+                .iter_mut()
+                .chain(stroke.curve.iter_mut())
+            {
+                m.point = affine.transform_point2(m.point);
+            }
+        }
+    }
+    /// Time between the start of the group and its last measurement.
+    pub fn duration(&self) -> Duration {
+        self.strokes
+            .iter()
+            .filter_map(|stroke| stroke.measurements.last())
+            .map(|m| m.time)
+            .max()
+            .unwrap_or_default()
     }
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 pub struct Stroke {
+    pub style: StrokeStyle,
+    /// Raw samples as they were recorded.
     pub measurements: Vec<PointMeasurement>,
+    /// Smooth curve fitted through `measurements` once the stroke is finished.
+    pub curve: Vec<PointMeasurement>,
+}
+
+impl Stroke {
+    pub fn new(style: StrokeStyle) -> Self {
+        Self {
+            style,
+            ..Default::default()
+        }
+    }
+    /// The shape of the stroke, the fitted curve if there is one, otherwise the raw samples.
+    pub fn curve(&self) -> &[PointMeasurement] {
+        if self.curve.is_empty() {
+            &self.measurements
+        } else {
+            &self.curve
+        }
+    }
+    /// (Re)fit the smooth curve from the raw samples.
+    pub fn fit_curve(&mut self, settings: &SmoothingSettings) {
+        self.curve = smooth::smooth(&self.measurements, settings);
+    }
+    /// Decimate the raw samples and fit the curve, `tolerance` is in the local units of the group.
+    pub fn finish(&mut self, smoothing: &SmoothingSettings, tolerance: f32) {
+        let recorded = self.measurements.len();
+        self.measurements = simplify::simplify(&self.measurements, tolerance);
+        self.fit_curve(smoothing);
+        self.curve = simplify::simplify(&self.curve, tolerance);
+        debug!(
+            "stroke finished, kept {} of {} samples, {} curve points",
+            self.measurements.len(),
+            recorded,
+            self.curve.len()
+        );
+    }
+    /// Whether a circle at `point` touches the inked area of the stroke.
+    pub fn hit(&self, point: Vec2, radius: f32, pressure: &PressureSettings) -> bool {
+        let reach =
+            |m: &PointMeasurement| radius + pressure.stroke_width(&self.style, m.press) * 0.5;
+        match self.curve() {
+            [] => false,
+            [m] => m.point.distance(point) <= reach(m),
+            curve => curve.windows(2).any(|w| {
+                simplify::distance_to_segment(point, w[0].point, w[1].point)
+                    <= reach(&w[0]).max(reach(&w[1]))
+            }),
+        }
+    }
+    /// Bounding box of the inked area, `None` for a stroke without points.
+    pub fn bounds(&self, pressure: &PressureSettings) -> Option<Rect> {
+        self.curve()
+            .iter()
+            .map(|m| {
+                let radius = pressure.stroke_width(&self.style, m.press) * 0.5;
+                Rect::from_center_half_size(m.point, Vec2::splat(radius))
+            })
+            .reduce(|a, b| a.union(b))
+    }
+    /// Build a single mesh for the whole stroke, in the local space of its group.
+    /// The line width follows the recorded pressure.
+    pub fn tessellate(&self, pressure: &PressureSettings, detail: &Detail) -> Mesh {
+        let mut builder = tessellate::StrokeMeshBuilder::new();
+        if detail.tolerance > 0.0 {
+            let curve = simplify::simplify(self.curve(), detail.tolerance);
+            builder.add_measurements(&curve, &self.style, pressure, detail.min_width);
+        } else {
+            builder.add_measurements(self.curve(), &self.style, pressure, detail.min_width);
+        }
+        builder.build()
+    }
 }
-impl Stroke {}
-#[derive(Debug)]
+
+#[derive(Debug, Clone, Copy)]
 pub struct PointMeasurement {
     pub point: Vec2,
     pub press: Option<f32>,
+    /// Capture time, relative to [`StrokeGroup::started_at`].
+    pub time: Duration,
 }
 
 impl PointMeasurement {
     pub fn new_point(point: Vec2) -> Self {
-        Self { point, press: None }
+        Self {
+            point,
+            press: None,
+            time: Duration::ZERO,
+        }
     }
     pub fn with_press(mut self, press: f32) -> Self {
         self.press = Some(press);
         self
     }
+    pub fn with_time(mut self, time: Duration) -> Self {
+        self.time = time;
+        self
+    }
+    /// Linear interpolation of every measured value.
+    pub fn lerp(&self, rhs: &Self, t: f32) -> Self {
+        let press = match (self.press, rhs.press) {
+            (Some(a), Some(b)) => Some(a + (b - a) * t),
+            (a, b) => a.or(b),
+        };
+        Self {
+            point: self.point.lerp(rhs.point, t),
+            press,
+            time: self.time.mul_f32(1.0 - t) + rhs.time.mul_f32(t),
+        }
+    }
+}
+
+/// How recorded pressure maps to ink.
+#[derive(Debug, Resource)]
+pub struct PressureSettings {
+    /// Pressure recorded for devices that don't report any, like mice.
+    pub mouse_pressure: f32,
+    /// Width of the thinnest possible line, relative to the full stroke width.
+    pub min_width_ratio: f32,
+}
+
+impl Default for PressureSettings {
+    fn default() -> Self {
+        Self {
+            mouse_pressure: 0.5,
+            min_width_ratio: 0.2,
+        }
+    }
+}
+
+impl PressureSettings {
+    /// Line width for a measured pressure, pressure is clamped to `0.0..=1.0`.
+    /// Measurements without pressure are drawn at full width.
+    pub fn width(&self, base_width: f32, press: Option<f32>) -> f32 {
+        let press = press.unwrap_or(1.0).clamp(0.0, 1.0);
+        base_width * (self.min_width_ratio + (1.0 - self.min_width_ratio) * press)
+    }
+    /// Line width of a stroke, only some kinds of brushes follow the pressure.
+    pub fn stroke_width(&self, style: &StrokeStyle, press: Option<f32>) -> f32 {
+        if style.kind.pressure_sensitive() {
+            self.width(style.width, press)
+        } else {
+            style.width
+        }
+    }
+}
+
+/// Components of a finished stroke group, placed at `transform` relative to its parent.
+pub fn stroke_group_bundle(
+    stroke_group: StrokeGroup,
+    transform: Transform,
+    pressure: &PressureSettings,
+) -> impl Bundle {
+    (
+        Region::new(stroke_group.bounds(pressure)),
+        stroke_group,
+        Unit { layer: 0 },
+        SpatialBundle {
+            transform,
+            ..Default::default()
+        },
+    )
+}
+
+fn normalized_force(force: ForceTouch) -> f32 {
+    match force {
+        ForceTouch::Calibrated {
+            force,
+            max_possible_force,
+            ..
+        } => (force / max_possible_force) as f32,
+        ForceTouch::Normalized(force) => force as f32,
+    }
 }
 
 pub fn stroke_record_system(
     mut commands: Commands,
     mouse_button: Res<ButtonInput<MouseButton>>,
+    touches: Res<Touches>,
     tool_box: Res<ToolBox>,
+    pressure_settings: Res<PressureSettings>,
+    smoothing_settings: Res<SmoothingSettings>,
+    simplify_settings: Res<SimplifySettings>,
     mut cursor_moved_events: EventReader<CursorMoved>,
-    mut q_window: Query<&Window, With<PrimaryWindow>>,
+    mut touch_events: EventReader<TouchInput>,
+    q_window: Query<&Window, With<PrimaryWindow>>,
     mut q_stroke: Query<
         (
             Entity,
@@ -70,30 +304,32 @@ pub fn stroke_record_system(
         With<Active>,
     >,
     q_board: Query<(Entity, &GlobalTransform), With<Board>>,
-    q_camera: Query<(&Camera, &GlobalTransform), With<Global2DCamera>>,
+    q_camera: Query<(&Camera, &GlobalTransform, &OrthographicProjection), With<Global2DCamera>>,
 ) {
     let (board_entity, board_gt) = q_board.single();
-    let (camera, camera_gt) = q_camera.single();
-    let Some(Tool::Brush {}) = tool_box.current_tool() else {
+    let (camera, camera_gt, projection) = q_camera.single();
+    let Some(Tool::Brush(brush)) = tool_box.current_tool() else {
         return;
     };
     // 1. how many active strokes are there?
     let mut active_strokes = q_stroke.iter_mut().collect::<Vec<_>>();
-    // 1.1 if there is no active stroke, and the left mouse button is just pressed, create a new stroke
-    if active_strokes.is_empty() && mouse_button.just_pressed(MouseButton::Left) {
+    let pen_pressed = mouse_button.pressed(MouseButton::Left) || touches.iter().next().is_some();
+    // 1.1 if there is no active stroke, and the pen is just pressed, create a new stroke
+    if active_strokes.is_empty()
+        && (mouse_button.just_pressed(MouseButton::Left) || touches.any_just_pressed())
+    {
         info!("creating_stroke_group start");
-        let translation = board_gt.translation();
         let window = q_window.single();
-        if let Some(cursor_position) = window.cursor_position() {
+        if let Some(cursor_position) = window
+            .cursor_position()
+            .or_else(|| touches.first_pressed_position())
+        {
             let Some(world_p) = camera.viewport_to_world_2d(camera_gt, cursor_position) else {
                 warn!("creating_stroke failed, no world point found");
                 return;
             };
-            let transform = Transform::from_translation(Vec3::new(
-                world_p.x - translation.x,
-                world_p.y - translation.y,
-                1.0,
-            ));
+            let transform =
+                Transform::from_translation(region::to_local(board_gt, world_p).extend(1.0));
             let id = commands
                 .spawn((
                     StrokeGroup::new(),
@@ -112,7 +348,7 @@ pub fn stroke_record_system(
                 "creating_stroke spawned a new stroke entity with id {:?}",
                 id
             );
-            if !mouse_button.pressed(MouseButton::Left) {
+            if !pen_pressed {
                 info!(
                     "creating_stroke finished in one poll, from {:?} to {:?}",
                     world_p, world_p
@@ -123,32 +359,63 @@ pub fn stroke_record_system(
     } else if active_strokes.len() == 1 {
         let (id, mut stroke_group, mut last_update, mut region, gt) = active_strokes.pop().unwrap();
         const STICKY_DURATION: std::time::Duration = std::time::Duration::from_secs(3);
-        if mouse_button.pressed(MouseButton::Left) {
-            if !cursor_moved_events.is_empty() {
+        // screen pixels to the local units of the group
+        let tolerance = simplify_settings.tolerance * projection.scale / region::local_scale(gt);
+        if pen_pressed {
+            // mice report no pressure, touch and pen input may report a force
+            let samples = cursor_moved_events
+                .read()
+                .map(|event| (event.position, pressure_settings.mouse_pressure))
+                .chain(
+                    touch_events
+                        .read()
+                        .filter(|event| {
+                            matches!(event.phase, TouchPhase::Started | TouchPhase::Moved)
+                        })
+                        .map(|event| {
+                            let press = event
+                                .force
+                                .map(normalized_force)
+                                .unwrap_or(pressure_settings.mouse_pressure);
+                            (event.position, press)
+                        }),
+                )
+                .collect::<Vec<_>>();
+            if !samples.is_empty() {
                 last_update.update();
             }
-            let translation = gt.translation();
-            for event in cursor_moved_events.read() {
-                let Some(world_p) = camera.viewport_to_world_2d(camera_gt, event.position) else {
+            let to_local = gt.affine().inverse();
+            let time = stroke_group.started_at.elapsed();
+            for (position, press) in samples {
+                let Some(world_p) = camera.viewport_to_world_2d(camera_gt, position) else {
                     warn!("creating_stroke add point failed, no world point found");
                     continue;
                 };
-                let current_stroke = stroke_group.active_stroke.get_or_insert(Default::default());
-                let point = Vec2::new(world_p.x - translation.x, world_p.y - translation.y);
-                current_stroke
-                    .measurements
-                    .push(PointMeasurement::new_point(point));
+                let current_stroke = stroke_group
+                    .active_stroke
+                    .get_or_insert_with(|| Stroke::new(brush.style()));
+                let point = to_local.transform_point3(world_p.extend(0.0)).truncate();
+                current_stroke.measurements.push(
+                    PointMeasurement::new_point(point)
+                        .with_press(press)
+                        .with_time(time),
+                );
                 region.rect = region.rect.union_point(point);
             }
-        } else if let Some(finished) = stroke_group.active_stroke.take() {
+        } else if let Some(mut finished) = stroke_group.active_stroke.take() {
+            finished.finish(&smoothing_settings, tolerance);
             stroke_group.strokes.push(finished);
+            // the raw samples leave out the line width and the smoothed curve
+            region.rect = stroke_group.bounds(&pressure_settings);
             last_update.update();
             debug!("creating_stroke finished");
         }
         if last_update.0.elapsed() > STICKY_DURATION {
-            if let Some(last_stroke) = stroke_group.active_stroke.take() {
+            if let Some(mut last_stroke) = stroke_group.active_stroke.take() {
                 if !last_stroke.measurements.is_empty() {
+                    last_stroke.finish(&smoothing_settings, tolerance);
                     stroke_group.strokes.push(last_stroke);
+                    region.rect = stroke_group.bounds(&pressure_settings);
                 }
             }
             commands.entity(id).remove::<Active>();
@@ -163,55 +430,310 @@ pub fn stroke_record_system(
     }
 }
 
+/// Child mesh of a finished stroke, children are kept in the order of [`StrokeGroup::strokes`].
+#[derive(Component)]
+pub struct StrokeMesh;
+
+/// Measurements after which a chunk of a live stroke is closed and a new one started.
+const LIVE_CHUNK: usize = 64;
+/// Live chunks are only closed where the stroke turns less than this, so the cut between two
+/// chunks stays inside the ink.
+const MAX_CUT_TURN: f32 = PI / 9.0;
+
+/// Child mesh of a chunk of [`StrokeGroup::active_stroke`].
+///
+/// Only the last chunk is built again as new points come in, so the work per frame does not
+/// grow with the length of the stroke.
+#[derive(Component, Default)]
+pub struct LiveStroke {
+    /// First measurement of the chunk, the last one of the chunk before.
+    start: usize,
+    /// Measurements before this one are in the mesh.
+    end: usize,
+}
+
+impl LiveStroke {
+    /// Mesh the measurements of the chunk up to `end`, cut where other chunks go on.
+    fn mesh(&mut self, stroke: &Stroke, end: usize, pressure: &PressureSettings) -> Mesh {
+        use tessellate::SectionEnd;
+        let measurements = &stroke.measurements;
+        let start = match self.start {
+            0 => SectionEnd::Cap,
+            start => SectionEnd::Cut(measurements[start - 1].point),
+        };
+        let cut = measurements
+            .get(end)
+            .map_or(SectionEnd::Cap, |m| SectionEnd::Cut(m.point));
+        let mut builder = tessellate::StrokeMeshBuilder::new();
+        builder.add_measured_section(
+            &measurements[self.start..end],
+            &stroke.style,
+            pressure,
+            0.0,
+            start,
+            cut,
+        );
+        self.end = end;
+        builder.build()
+    }
+    /// Where to close the chunk once it is full, the end of the chunk after a point where the
+    /// stroke turns gently.
+    fn close_at(&self, stroke: &Stroke) -> Option<usize> {
+        let points = &stroke.measurements;
+        // points before the end of the mesh were already looked at
+        let first = (self.start + LIVE_CHUNK).max(self.end.saturating_sub(1));
+        (first..points.len().saturating_sub(1))
+            .find(|&i| {
+                let before = points[i].point - points[i - 1].point;
+                let after = points[i + 1].point - points[i].point;
+                before.length() >= 1.0
+                    && after.length() >= 1.0
+                    && before.angle_between(after).abs() < MAX_CUT_TURN
+            })
+            .map(|i| i + 1)
+    }
+}
+
+/// Throw away the meshes of rendered groups whose strokes were edited, so they are rendered again.
+///
+/// Dropping the render entities drops their mesh handles, which frees the mesh assets.
+pub fn invalidate_strokes_system(
+    mut commands: Commands,
+    q_group: Query<(Entity, Option<&Children>), (Changed<StrokeGroup>, With<Rendered>)>,
+    q_render: Query<(), Or<(With<StrokeMesh>, With<LiveStroke>, With<RasterSprite>)>>,
+) {
+    for (entity, children) in q_group.iter() {
+        for &child in children.into_iter().flatten() {
+            if q_render.contains(child) {
+                commands.entity(child).despawn_recursive();
+            }
+        }
+        commands.entity(entity).remove::<(Rendered, RasterCache)>();
+    }
+}
+
+/// Render strokes of groups that are being drawn or were not rendered yet.
+///
+/// Only the strokes finished since the last frame get a new mesh, and the last chunk of the
+/// stroke being drawn is meshed again only when it got new points.
 pub fn render_strokes_system(
     mut commands: Commands,
-    mut materials: ResMut<Assets<ColorMaterial>>,
-    q_inactive: Query<(Entity, &StrokeGroup), (Without<Active>, Without<Rendered>)>,
-    q_active: Query<(Entity, &StrokeGroup), With<Active>>,
+    mut ink_materials: InkMaterials,
     mut meshes: ResMut<Assets<Mesh>>,
+    pressure_settings: Res<PressureSettings>,
+    lod_settings: Res<LodSettings>,
+    q_camera: Query<&OrthographicProjection, With<Global2DCamera>>,
+    q_group: Query<
+        (
+            Entity,
+            &StrokeGroup,
+            &GlobalTransform,
+            Option<&Children>,
+            Has<Active>,
+        ),
+        Without<Rendered>,
+    >,
+    q_stroke_mesh: Query<(), With<StrokeMesh>>,
+    mut q_live: Query<(&mut LiveStroke, &Mesh2dHandle)>,
 ) {
-    let handle = materials.add(Color::PURPLE);
-    let mesh = Mesh::from(bevy::math::prelude::Rectangle::new(10.0, 10.0));
-    for (entity, stroke_group) in q_inactive.iter() {
-        info!("rendering_stroke start");
-        for stroke in &stroke_group.strokes {
-            for measurement in &stroke.measurements {
-                let mesh_handle = meshes.add(mesh.clone());
-                commands
-                    .spawn(ColorMesh2dBundle {
-                        mesh: mesh_handle.into(),
-                        material: handle.clone(),
-                        transform: Transform::from_translation(Vec3::new(
-                            measurement.point.x,
-                            measurement.point.y,
-                            2.0,
-                        )),
-                        ..Default::default()
-                    })
-                    .set_parent(entity);
+    let level = lod_settings.level(q_camera.single().scale);
+    for (entity, stroke_group, gt, children, active) in q_group.iter() {
+        let detail = lod_settings.detail(level, region::local_scale(gt));
+        let children = children.map(|c| &**c).unwrap_or_default();
+        let rendered = children
+            .iter()
+            .filter(|&&child| q_stroke_mesh.contains(child))
+            .count();
+        for stroke in stroke_group.strokes.iter().skip(rendered) {
+            let mesh = meshes.add(stroke.tessellate(&pressure_settings, &detail));
+            let id = ink_materials.spawn_mesh(&mut commands, mesh, &stroke.style, StrokeMesh);
+            commands.entity(id).set_parent(entity);
+        }
+        let chunks = children
+            .iter()
+            .copied()
+            .filter(|&child| q_live.contains(child))
+            .collect::<Vec<_>>();
+        let last = chunks
+            .iter()
+            .copied()
+            .max_by_key(|&chunk| q_live.get(chunk).map_or(0, |(live, _)| live.start));
+        match (&stroke_group.active_stroke, last) {
+            (Some(stroke), Some(last)) => {
+                let (mut live, mesh) = q_live.get_mut(last).unwrap();
+                let len = stroke.measurements.len();
+                if live.end != len {
+                    let end = live.close_at(stroke).unwrap_or(len);
+                    if let Some(mesh) = meshes.get_mut(&mesh.0) {
+                        *mesh = live.mesh(stroke, end, &pressure_settings);
+                    }
+                    if end != len {
+                        // the rest goes into a new chunk, starting at the last point of this one
+                        let mut live = LiveStroke {
+                            start: end - 1,
+                            ..default()
+                        };
+                        let mesh = meshes.add(live.mesh(stroke, len, &pressure_settings));
+                        let id = ink_materials.spawn_mesh(&mut commands, mesh, &stroke.style, live);
+                        commands.entity(id).set_parent(entity);
+                    }
+                }
+            }
+            (Some(stroke), None) => {
+                let mut live = LiveStroke::default();
+                let mesh = live.mesh(stroke, stroke.measurements.len(), &pressure_settings);
+                let mesh = meshes.add(mesh);
+                let id = ink_materials.spawn_mesh(&mut commands, mesh, &stroke.style, live);
+                commands.entity(id).set_parent(entity);
+            }
+            (None, _) => {
+                for chunk in chunks {
+                    commands.entity(chunk).despawn_recursive();
+                }
             }
         }
-        commands.entity(entity).insert(Rendered);
-        info!("rendering_stroke finished");
-    }
-    for (entity, stroke_group) in q_active.iter() {
-        commands.entity(entity).clear_children();
-        for stroke in &stroke_group.strokes {
-            for measurement in &stroke.measurements {
-                let mesh_handle = meshes.add(mesh.clone());
-                commands
-                    .spawn(ColorMesh2dBundle {
-                        mesh: mesh_handle.into(),
-                        material: handle.clone(),
-                        transform: Transform::from_translation(Vec3::new(
-                            measurement.point.x,
-                            measurement.point.y,
-                            2.0,
-                        )),
-                        ..Default::default()
-                    })
-                    .set_parent(entity);
+        commands.entity(entity).insert(Lod(level));
+        if !active {
+            commands.entity(entity).insert(Rendered);
+            debug!("rendering_stroke finished");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use material::{HighlighterMaterial, StrokeMaterials};
+
+    const FRAMES: usize = 20;
+
+    fn app() -> App {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, AssetPlugin::default()))
+            .init_asset::<Mesh>()
+            .init_asset::<Image>()
+            .init_asset::<ColorMaterial>()
+            .init_asset::<HighlighterMaterial>()
+            .init_resource::<StrokeMaterials>()
+            .init_resource::<PressureSettings>()
+            .init_resource::<LodSettings>()
+            .add_systems(
+                Update,
+                (invalidate_strokes_system, render_strokes_system).chain(),
+            );
+        app.world
+            .spawn((OrthographicProjection::default(), Global2DCamera));
+        app
+    }
+
+    fn stroke() -> Stroke {
+        Stroke {
+            measurements: (0..50)
+                .map(|i| PointMeasurement::new_point(Vec2::new(i as f32, (i as f32).sin())))
+                .collect(),
+            ..Stroke::new(StrokeStyle::default())
+        }
+    }
+
+    /// Meshes, materials and entities after a frame.
+    fn counts(app: &mut App) -> (usize, usize, usize) {
+        app.update();
+        (
+            app.world.resource::<Assets<Mesh>>().len(),
+            app.world.resource::<Assets<ColorMaterial>>().len(),
+            app.world.entities().len() as usize,
+        )
+    }
+
+    #[test]
+    fn active_group_keeps_its_live_mesh() {
+        let mut app = app();
+        let mut group = StrokeGroup::new();
+        group.strokes.push(stroke());
+        group.active_stroke = Some(stroke());
+        app.world.spawn((group, Active, SpatialBundle::default()));
+        let first = counts(&mut app);
+        for _ in 0..FRAMES {
+            assert_eq!(counts(&mut app), first);
+        }
+        // one mesh for the finished stroke, one for the live one
+        assert_eq!(first.0, 2);
+    }
+
+    #[test]
+    fn edited_group_frees_its_old_meshes() {
+        let mut app = app();
+        let mut group = StrokeGroup::new();
+        group.strokes.extend([stroke(), stroke()]);
+        let entity = app.world.spawn((group, SpatialBundle::default())).id();
+        counts(&mut app);
+        assert!(app.world.get::<Rendered>(entity).is_some());
+        let edit = |app: &mut App| {
+            app.world
+                .get_mut::<StrokeGroup>(entity)
+                .unwrap()
+                .set_changed();
+            counts(app)
+        };
+        // dropped meshes are freed the frame after, counts settle after one edit
+        edit(&mut app);
+        let settled = edit(&mut app);
+        for _ in 0..FRAMES {
+            assert_eq!(edit(&mut app), settled);
+        }
+        counts(&mut app);
+        assert_eq!(counts(&mut app).0, 2);
+    }
+
+    #[test]
+    fn long_live_stroke_only_meshes_its_last_chunk() {
+        let mut app = app();
+        let mut group = StrokeGroup::new();
+        group.active_stroke = Some(Stroke::new(StrokeStyle::default()));
+        let entity = app
+            .world
+            .spawn((group, Active, SpatialBundle::default()))
+            .id();
+        let mut uploaded = Vec::new();
+        for frame in 0..100 {
+            let mut group = app.world.get_mut::<StrokeGroup>(entity).unwrap();
+            let stroke = group.active_stroke.as_mut().unwrap();
+            for i in frame * 10..(frame + 1) * 10 {
+                let t = i as f32 * 0.05;
+                let point = Vec2::new(t * 40.0, t.sin() * 40.0);
+                stroke.measurements.push(PointMeasurement::new_point(point));
             }
+            app.update();
+            let events = app
+                .world
+                .resource_mut::<Events<AssetEvent<Mesh>>>()
+                .drain()
+                .collect::<Vec<_>>();
+            let meshes = app.world.resource::<Assets<Mesh>>();
+            let vertices = events
+                .iter()
+                .filter_map(|event| match event {
+                    AssetEvent::Added { id } | AssetEvent::Modified { id } => meshes.get(*id),
+                    _ => None,
+                })
+                .map(Mesh::count_vertices)
+                .sum::<usize>();
+            uploaded.push(vertices);
         }
+        let meshes = app.world.resource::<Assets<Mesh>>();
+        let total = meshes
+            .iter()
+            .map(|(_, mesh)| mesh.count_vertices())
+            .sum::<usize>();
+        assert!(meshes.len() > 10);
+        // the work per frame stays far below the whole stroke
+        assert!(uploaded.iter().all(|&vertices| vertices < total / 5));
+
+        // finishing the stroke drops every chunk
+        let mut group = app.world.get_mut::<StrokeGroup>(entity).unwrap();
+        group.active_stroke = None;
+        app.update();
+        app.update();
+        assert_eq!(app.world.resource::<Assets<Mesh>>().len(), 0);
     }
 }
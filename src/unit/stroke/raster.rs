@@ -0,0 +1,283 @@
+//! Optional raster cache, finished groups are drawn as one texture instead of their meshes.
+//!
+//! `F10` toggles the cache. A group is rendered into its texture by a camera that lives for a
+//! single frame, the texture is rendered again when the zoom changed a lot or the group is
+//! edited. Only groups with opaque ink are cached: highlighter ink needs what is below it, and
+//! translucent ink would come out of the texture premultiplied and be blended a second time.
+use bevy::{
+    prelude::*,
+    render::{
+        camera::{RenderTarget, ScalingMode},
+        render_asset::RenderAssetUsages,
+        render_resource::{Extent3d, TextureDimension, TextureFormat, TextureUsages},
+        texture::BevyDefault,
+        view::RenderLayers,
+    },
+};
+
+use crate::{
+    camera::Global2DCamera,
+    tools::{
+        brush::BrushKind,
+        picker::region::{self, Region},
+    },
+    unit::cull::VisibleUnits,
+};
+
+use super::{
+    material::{ink_depth, opaque_ink},
+    Active, Rendered, StrokeGroup, StrokeMesh,
+};
+
+/// Layer seen by the cache cameras only.
+const CACHE_LAYER: u8 = 1;
+/// Layer seen by no camera, the meshes of cached groups are kept there.
+const STASH_LAYER: u8 = 2;
+/// Zoom factor after which a cached texture is rendered again.
+const RESCALE_RATIO: f32 = 2.0;
+
+#[derive(Debug, Resource)]
+pub struct RasterCacheSettings {
+    pub enabled: bool,
+    /// Largest side of a cached texture, in pixels.
+    pub max_size: u32,
+}
+
+impl Default for RasterCacheSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_size: 2048,
+        }
+    }
+}
+
+/// The group is drawn from a cached texture, rendered at camera scale `scale`.
+#[derive(Debug, Component)]
+pub struct RasterCache {
+    scale: f32,
+}
+
+/// Child sprite showing the cached texture of its group.
+#[derive(Component)]
+pub struct RasterSprite;
+
+/// Child camera rendering its group into the cached texture, despawned the next frame.
+#[derive(Component)]
+pub struct RasterCamera;
+
+pub fn raster_cache_system(
+    mut commands: Commands,
+    kbd_input: Res<ButtonInput<KeyCode>>,
+    mut settings: ResMut<RasterCacheSettings>,
+    visible_units: Res<VisibleUnits>,
+    mut images: ResMut<Assets<Image>>,
+    q_camera: Query<&OrthographicProjection, With<Global2DCamera>>,
+    q_group: Query<
+        (
+            &StrokeGroup,
+            &Region,
+            &GlobalTransform,
+            &Children,
+            Option<&RasterCache>,
+        ),
+        (With<Rendered>, Without<Active>),
+    >,
+    q_cached: Query<(Entity, &Children), With<RasterCache>>,
+    q_stroke_mesh: Query<(), With<StrokeMesh>>,
+    mut q_sprite: Query<&mut Visibility, With<RasterSprite>>,
+    q_raster_camera: Query<(Entity, &Parent), With<RasterCamera>>,
+) {
+    if kbd_input.just_pressed(KeyCode::F10) {
+        settings.enabled = !settings.enabled;
+        info!("raster cache enabled {}", settings.enabled);
+    }
+    // the cameras spawned last frame have rendered, swap the meshes for the textures
+    for (camera, parent) in q_raster_camera.iter() {
+        commands.entity(camera).despawn_recursive();
+        let Ok((_, children)) = q_cached.get(parent.get()) else {
+            continue;
+        };
+        for &child in children {
+            if q_stroke_mesh.contains(child) {
+                commands
+                    .entity(child)
+                    .insert(RenderLayers::layer(STASH_LAYER));
+            } else if let Ok(mut visibility) = q_sprite.get_mut(child) {
+                *visibility = Visibility::Inherited;
+            }
+        }
+    }
+    if !settings.enabled {
+        for (entity, children) in q_cached.iter() {
+            for &child in children {
+                if q_stroke_mesh.contains(child) {
+                    commands.entity(child).remove::<RenderLayers>();
+                } else if q_sprite.contains(child) {
+                    commands.entity(child).despawn_recursive();
+                }
+            }
+            commands.entity(entity).remove::<RasterCache>();
+        }
+        return;
+    }
+    let scale = q_camera.single().scale;
+    // one group per frame, cache cameras would see every group on the cache layer
+    let stale = visible_units.iter().copied().find(|&entity| {
+        q_group.get(entity).is_ok_and(|(group, region, .., cache)| {
+            let fresh = cache.is_some_and(|cache| {
+                (1.0 / RESCALE_RATIO..=RESCALE_RATIO).contains(&(scale / cache.scale))
+            });
+            !fresh
+                && region.rect.size().min_element() > 0.0
+                && group.strokes.iter().all(|stroke| opaque_ink(&stroke.style))
+        })
+    });
+    let Some(entity) = stale else {
+        return;
+    };
+    let (_, region, gt, children, _) = q_group.get(entity).unwrap();
+    let size = region.rect.size();
+    let pixels = size * region::local_axis_scales(gt) / scale;
+    let fit = (settings.max_size as f32 / pixels.max_element()).min(1.0);
+    let pixels = (pixels * fit).ceil().max(Vec2::ONE).as_uvec2();
+    let mut image = Image::new_fill(
+        Extent3d {
+            width: pixels.x,
+            height: pixels.y,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        &[0; 4],
+        TextureFormat::bevy_default(),
+        RenderAssetUsages::default(),
+    );
+    image.texture_descriptor.usage =
+        TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST | TextureUsages::RENDER_ATTACHMENT;
+    let image = images.add(image);
+    for &child in children {
+        if q_stroke_mesh.contains(child) {
+            commands
+                .entity(child)
+                .insert(RenderLayers::from_layers(&[0, CACHE_LAYER]));
+        } else if q_sprite.contains(child) {
+            commands.entity(child).despawn_recursive();
+        }
+    }
+    let center = region.rect.center();
+    let mut camera = Camera2dBundle::default();
+    camera.camera.target = RenderTarget::Image(image.clone());
+    camera.camera.order = -1;
+    camera.camera.clear_color = ClearColorConfig::Custom(Color::NONE);
+    camera.projection.scaling_mode = ScalingMode::Fixed {
+        width: size.x,
+        height: size.y,
+    };
+    camera.transform.translation = center.extend(camera.transform.translation.z);
+    commands.entity(entity).with_children(|parent| {
+        parent.spawn((camera, RenderLayers::layer(CACHE_LAYER), RasterCamera));
+        parent.spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    custom_size: Some(size),
+                    ..default()
+                },
+                texture: image,
+                transform: Transform::from_translation(
+                    center.extend(ink_depth(BrushKind::default())),
+                ),
+                visibility: Visibility::Hidden,
+                ..default()
+            },
+            RasterSprite,
+        ));
+    });
+    commands.entity(entity).insert(RasterCache { scale });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        tools::brush::StrokeStyle,
+        unit::{
+            cull, index,
+            stroke::{PointMeasurement, Stroke},
+            Unit,
+        },
+    };
+
+    const FRAMES: usize = 4;
+
+    fn group(style: StrokeStyle) -> impl Bundle {
+        let stroke = Stroke {
+            measurements: vec![
+                PointMeasurement::new_point(Vec2::ZERO),
+                PointMeasurement::new_point(Vec2::new(20.0, 10.0)),
+            ],
+            ..Stroke::new(style)
+        };
+        let mut group = StrokeGroup::new();
+        group.strokes.push(stroke);
+        (
+            group,
+            Region::new(Rect::new(0.0, 0.0, 20.0, 10.0)),
+            Unit { layer: 0 },
+            Rendered,
+            SpatialBundle::default(),
+        )
+    }
+
+    #[test]
+    fn only_opaque_ink_is_cached() {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, AssetPlugin::default()))
+            .init_asset::<Image>()
+            .insert_resource(ButtonInput::<KeyCode>::default())
+            .insert_resource(RasterCacheSettings {
+                enabled: true,
+                ..default()
+            })
+            .init_resource::<index::UnitIndex>()
+            .init_resource::<VisibleUnits>()
+            .add_systems(
+                Update,
+                (
+                    index::update_unit_index_system,
+                    cull::cull_units_system,
+                    raster_cache_system,
+                )
+                    .chain(),
+            );
+        app.world.spawn((
+            OrthographicProjection::default(),
+            GlobalTransform::default(),
+            Global2DCamera,
+        ));
+        let translucent = StrokeStyle {
+            opacity: 0.6,
+            ..default()
+        };
+        let pencil = StrokeStyle {
+            kind: BrushKind::Pencil,
+            ..default()
+        };
+        let groups = [StrokeStyle::default(), translucent, pencil].map(|style| {
+            let mesh = app.world.spawn(StrokeMesh).id();
+            app.world.spawn(group(style)).add_child(mesh).id()
+        });
+        for _ in 0..FRAMES {
+            app.update();
+        }
+        // translucent groups keep being drawn by their meshes, which blend their ink once
+        let cached = groups.map(|group| {
+            let mesh = app.world.get::<Children>(group).unwrap()[0];
+            assert_eq!(
+                app.world.get::<RasterCache>(group).is_some(),
+                app.world.get::<RenderLayers>(mesh) == Some(&RenderLayers::layer(STASH_LAYER)),
+            );
+            app.world.get::<RasterCache>(group).is_some()
+        });
+        assert_eq!(cached, [true, false, false]);
+    }
+}
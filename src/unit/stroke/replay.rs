@@ -0,0 +1,164 @@
+//! Replays how stroke groups were written.
+//!
+//! `P` replays the groups selected with the picker, or the whole board when nothing is
+//! selected, and stops a running replay. `,` and `.` halve and double the replay speed.
+use bevy::{
+    prelude::*,
+    sprite::Mesh2dHandle,
+    utils::{HashMap, HashSet},
+};
+use std::time::Duration;
+
+use crate::{tools::ToolBox, unit::group::UnitTree};
+
+use super::{
+    material::InkMaterials, tessellate::StrokeMeshBuilder, Active, PressureSettings, StrokeGroup,
+};
+
+/// Pause between two groups replayed one after another.
+const REPLAY_GAP: Duration = Duration::from_millis(500);
+const REPLAY_SPEED_RANGE: std::ops::RangeInclusive<f32> = 0.125..=16.0;
+
+#[derive(Debug, Resource)]
+pub struct Replay {
+    /// Playback speed, `1.0` is real time.
+    pub speed: f32,
+    clock: Duration,
+    /// Groups being replayed and when each of them starts on the replay clock.
+    queue: HashMap<Entity, Duration>,
+    end: Duration,
+}
+
+impl Default for Replay {
+    fn default() -> Self {
+        Self {
+            speed: 1.0,
+            clock: Duration::ZERO,
+            queue: HashMap::new(),
+            end: Duration::ZERO,
+        }
+    }
+}
+
+impl Replay {
+    pub fn is_playing(&self) -> bool {
+        !self.queue.is_empty()
+    }
+    pub fn scale_speed(&mut self, factor: f32) {
+        self.speed =
+            (self.speed * factor).clamp(*REPLAY_SPEED_RANGE.start(), *REPLAY_SPEED_RANGE.end());
+        info!("replay speed {}", self.speed);
+    }
+}
+
+/// Mesh showing the replayed part of a stroke, the group's own meshes are hidden meanwhile.
+#[derive(Component)]
+pub struct ReplayStroke {
+    /// Index into [`StrokeGroup::strokes`].
+    index: usize,
+}
+
+pub fn replay_system(
+    mut commands: Commands,
+    time: Res<Time>,
+    kbd_input: Res<ButtonInput<KeyCode>>,
+    tool_box: Res<ToolBox>,
+    pressure_settings: Res<PressureSettings>,
+    mut replay: ResMut<Replay>,
+    mut ink_materials: InkMaterials,
+    mut meshes: ResMut<Assets<Mesh>>,
+    unit_tree: UnitTree,
+    q_group: Query<(Entity, &StrokeGroup, Option<&Children>), Without<Active>>,
+    q_replay_stroke: Query<(Entity, &ReplayStroke, &Parent, &Mesh2dHandle)>,
+    mut q_visibility: Query<&mut Visibility, Without<ReplayStroke>>,
+) {
+    if kbd_input.just_pressed(KeyCode::Comma) {
+        replay.scale_speed(0.5);
+    }
+    if kbd_input.just_pressed(KeyCode::Period) {
+        replay.scale_speed(2.0);
+    }
+    let stop = if kbd_input.just_pressed(KeyCode::KeyP) {
+        if replay.is_playing() {
+            true
+        } else {
+            // selected unit groups replay the stroke groups inside them
+            let selected = tool_box.picker().filter(|p| p.picked()).map(|p| {
+                p.selected
+                    .iter()
+                    .flat_map(|&entity| unit_tree.leaves(entity))
+                    .collect::<HashSet<_>>()
+            });
+            let mut targets = q_group
+                .iter()
+                .filter(|(entity, ..)| selected.as_ref().is_none_or(|s| s.contains(entity)))
+                .collect::<Vec<_>>();
+            targets.sort_by_key(|(_, group, _)| group.started_at);
+            let mut start = Duration::ZERO;
+            for (entity, group, children) in targets {
+                for &child in children.into_iter().flatten() {
+                    if let Ok(mut visibility) = q_visibility.get_mut(child) {
+                        *visibility = Visibility::Hidden;
+                    }
+                }
+                for (index, stroke) in group.strokes.iter().enumerate() {
+                    let mesh = meshes.add(StrokeMeshBuilder::new().build());
+                    let id = ink_materials.spawn_mesh(
+                        &mut commands,
+                        mesh,
+                        &stroke.style,
+                        ReplayStroke { index },
+                    );
+                    commands.entity(id).set_parent(entity);
+                }
+                replay.queue.insert(entity, start);
+                start += group.duration() + REPLAY_GAP;
+            }
+            replay.clock = Duration::ZERO;
+            replay.end = start;
+            info!("replay start, {} groups", replay.queue.len());
+            false
+        }
+    } else {
+        false
+    };
+    if !replay.is_playing() {
+        return;
+    }
+    let speed = replay.speed;
+    replay.clock += time.delta().mul_f32(speed);
+    if stop || replay.clock > replay.end {
+        for (entity, _, parent, _) in q_replay_stroke.iter() {
+            commands.entity(entity).despawn_recursive();
+            let Ok((_, _, Some(children))) = q_group.get(parent.get()) else {
+                continue;
+            };
+            for &child in children {
+                if let Ok(mut visibility) = q_visibility.get_mut(child) {
+                    *visibility = Visibility::Inherited;
+                }
+            }
+        }
+        replay.queue.clear();
+        info!("replay finished");
+        return;
+    }
+    for (_, replay_stroke, parent, mesh) in q_replay_stroke.iter() {
+        let (Some(start), Ok((_, group, _))) =
+            (replay.queue.get(&parent.get()), q_group.get(parent.get()))
+        else {
+            continue;
+        };
+        let (Some(elapsed), Some(stroke)) = (
+            replay.clock.checked_sub(*start),
+            group.strokes.get(replay_stroke.index),
+        ) else {
+            continue;
+        };
+        let curve = stroke.curve();
+        let end = curve.partition_point(|m| m.time <= elapsed);
+        let mut builder = StrokeMeshBuilder::new();
+        builder.add_measurements(&curve[..end], &stroke.style, &pressure_settings, 0.0);
+        meshes.insert(mesh.0.id(), builder.build());
+    }
+}
@@ -0,0 +1,172 @@
+//! Point decimation for finished strokes.
+use bevy::prelude::*;
+
+use super::PointMeasurement;
+
+/// Largest change of pressure that may be dropped, pressure drives the line width.
+const PRESSURE_TOLERANCE: f32 = 0.05;
+
+#[derive(Debug, Resource)]
+pub struct SimplifySettings {
+    /// Largest allowed deviation from the original samples, in screen pixels.
+    pub tolerance: f32,
+}
+
+impl Default for SimplifySettings {
+    fn default() -> Self {
+        Self { tolerance: 0.5 }
+    }
+}
+
+/// Ramer–Douglas–Peucker simplification.
+///
+/// A point is dropped when it lies within `tolerance` of the segment between the points kept
+/// around it, and its pressure can be interpolated from them.
+pub fn simplify(samples: &[PointMeasurement], tolerance: f32) -> Vec<PointMeasurement> {
+    if samples.len() < 3 || tolerance <= 0.0 {
+        return samples.to_vec();
+    }
+    let mut keep = vec![false; samples.len()];
+    keep[0] = true;
+    keep[samples.len() - 1] = true;
+    let mut pending = vec![(0, samples.len() - 1)];
+    while let Some((first, last)) = pending.pop() {
+        let (start, end) = (&samples[first], &samples[last]);
+        let mut worst = (0, 1.0);
+        for (i, sample) in samples.iter().enumerate().take(last).skip(first + 1) {
+            let t = (i - first) as f32 / (last - first) as f32;
+            let expected = start.lerp(end, t);
+            let distance = distance_to_segment(sample.point, start.point, end.point) / tolerance;
+            let pressure = match (sample.press, expected.press) {
+                (Some(a), Some(b)) => (a - b).abs() / PRESSURE_TOLERANCE,
+                _ => 0.0,
+            };
+            let error = distance.max(pressure);
+            if error > worst.1 {
+                worst = (i, error);
+            }
+        }
+        if worst.0 != 0 {
+            keep[worst.0] = true;
+            pending.push((first, worst.0));
+            pending.push((worst.0, last));
+        }
+    }
+    samples
+        .iter()
+        .zip(keep)
+        .filter_map(|(sample, keep)| keep.then_some(*sample))
+        .collect()
+}
+
+pub fn distance_to_segment(point: Vec2, a: Vec2, b: Vec2) -> f32 {
+    let ab = b - a;
+    let length_squared = ab.length_squared();
+    if length_squared <= f32::EPSILON {
+        return point.distance(a);
+    }
+    let t = ((point - a).dot(ab) / length_squared).clamp(0.0, 1.0);
+    point.distance(a + ab * t)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn points(points: impl IntoIterator<Item = Vec2>) -> Vec<PointMeasurement> {
+        points
+            .into_iter()
+            .map(PointMeasurement::new_point)
+            .collect()
+    }
+
+    /// Largest distance from an original sample to the simplified polyline.
+    fn max_deviation(samples: &[PointMeasurement], kept: &[PointMeasurement]) -> f32 {
+        samples
+            .iter()
+            .map(|sample| {
+                kept.windows(2)
+                    .map(|w| distance_to_segment(sample.point, w[0].point, w[1].point))
+                    .fold(f32::INFINITY, f32::min)
+            })
+            .fold(0.0, f32::max)
+    }
+
+    #[test]
+    fn collinear_points_keep_the_ends() {
+        let samples = points((0..100).map(|i| Vec2::new(i as f32, i as f32 * 0.5)));
+        let kept = simplify(&samples, 0.5);
+        assert_eq!(kept.len(), 2);
+        assert_eq!(kept[0].point, samples[0].point);
+        assert_eq!(kept[1].point, samples[99].point);
+    }
+
+    #[test]
+    fn noise_under_the_tolerance_is_dropped() {
+        let samples = points((0..200).map(|i| {
+            let noise = if i % 2 == 0 { 0.2 } else { -0.2 };
+            Vec2::new(i as f32, noise)
+        }));
+        assert_eq!(simplify(&samples, 0.5).len(), 2);
+        // a tolerance under the noise keeps most of it
+        assert!(simplify(&samples, 0.1).len() > 150);
+    }
+
+    #[test]
+    fn circle_keeps_enough_points_to_stay_within_tolerance() {
+        let samples =
+            points((0..=360).map(|degree| Vec2::from_angle((degree as f32).to_radians()) * 100.0));
+        let kept = simplify(&samples, 0.5);
+        // chords of a circle of radius 100 deviate by 0.5 over about 11.5 degrees
+        assert!((32..=64).contains(&kept.len()), "kept {}", kept.len());
+        assert!(max_deviation(&samples, &kept) <= 0.5);
+    }
+
+    #[test]
+    fn pressure_ramps_keep_their_turns() {
+        // a straight line whose pressure rises linearly, then falls linearly
+        let samples = (0..=100)
+            .map(|i| {
+                let press = if i <= 50 {
+                    i as f32 / 50.0
+                } else {
+                    (100 - i) as f32 / 50.0
+                };
+                PointMeasurement::new_point(Vec2::new(i as f32, 0.0)).with_press(press)
+            })
+            .collect::<Vec<_>>();
+        let kept = simplify(&samples, 0.5);
+        assert_eq!(kept.len(), 3);
+        assert_eq!(kept[1].press, Some(1.0));
+        // a linear ramp alone is interpolated exactly
+        assert_eq!(simplify(&samples[..=50], 0.5).len(), 2);
+    }
+
+    #[test]
+    fn short_inputs_and_zero_tolerance_are_kept() {
+        let samples = points([Vec2::ZERO, Vec2::X]);
+        assert_eq!(simplify(&samples, 0.5).len(), 2);
+        let samples = points((0..10).map(|i| Vec2::new(i as f32, 0.0)));
+        assert_eq!(simplify(&samples, 0.0).len(), 10);
+    }
+
+    #[test]
+    fn distance_to_segment_projects_onto_the_segment() {
+        let (a, b) = (Vec2::ZERO, Vec2::new(10.0, 0.0));
+        assert_eq!(distance_to_segment(Vec2::new(5.0, 3.0), a, b), 3.0);
+        assert_eq!(distance_to_segment(Vec2::new(5.0, -2.0), a, b), 2.0);
+    }
+
+    #[test]
+    fn distance_to_segment_is_measured_to_the_ends_past_them() {
+        let (a, b) = (Vec2::ZERO, Vec2::new(10.0, 0.0));
+        assert_eq!(distance_to_segment(Vec2::new(-3.0, 4.0), a, b), 5.0);
+        assert_eq!(distance_to_segment(Vec2::new(13.0, -4.0), a, b), 5.0);
+    }
+
+    #[test]
+    fn distance_to_degenerate_segment_is_to_its_point() {
+        let a = Vec2::new(1.0, 1.0);
+        assert_eq!(distance_to_segment(Vec2::new(4.0, 5.0), a, a), 5.0);
+    }
+}
@@ -0,0 +1,69 @@
+//! Curve fitting for finished strokes.
+use bevy::prelude::*;
+
+use super::PointMeasurement;
+
+/// Number of relaxation passes applied before fitting the curve.
+const RELAX_PASSES: usize = 3;
+
+#[derive(Debug, Resource)]
+pub struct SmoothingSettings {
+    /// How strongly jitter is removed, `0.0` keeps every sample on the curve.
+    pub strength: f32,
+    /// Points inserted between two samples on the fitted curve.
+    pub subdivisions: usize,
+}
+
+impl Default for SmoothingSettings {
+    fn default() -> Self {
+        Self {
+            strength: 0.5,
+            subdivisions: 4,
+        }
+    }
+}
+
+/// Fit a smooth curve through raw samples.
+///
+/// The samples are first relaxed towards their neighbours according to `strength`,
+/// then a Catmull-Rom spline through the relaxed points is sampled. End points are kept.
+pub fn smooth(samples: &[PointMeasurement], settings: &SmoothingSettings) -> Vec<PointMeasurement> {
+    if samples.len() < 3 {
+        return samples.to_vec();
+    }
+    let strength = settings.strength.clamp(0.0, 1.0);
+    let mut relaxed = samples.to_vec();
+    for _ in 0..RELAX_PASSES {
+        let previous = relaxed.clone();
+        for i in 1..previous.len() - 1 {
+            let target = (previous[i - 1].point + previous[i + 1].point) * 0.5;
+            relaxed[i].point = previous[i].point.lerp(target, strength * 0.5);
+        }
+    }
+    let steps = settings.subdivisions + 1;
+    let last = relaxed.len() - 1;
+    let mut curve = Vec::with_capacity(last * steps + 1);
+    for i in 0..last {
+        let p0 = relaxed[i.saturating_sub(1)].point;
+        let p1 = relaxed[i].point;
+        let p2 = relaxed[i + 1].point;
+        let p3 = relaxed[(i + 2).min(last)].point;
+        for step in 0..steps {
+            let t = step as f32 / steps as f32;
+            let mut measurement = relaxed[i].lerp(&relaxed[i + 1], t);
+            measurement.point = catmull_rom(p0, p1, p2, p3, t);
+            curve.push(measurement);
+        }
+    }
+    curve.push(relaxed[last]);
+    curve
+}
+
+fn catmull_rom(p0: Vec2, p1: Vec2, p2: Vec2, p3: Vec2, t: f32) -> Vec2 {
+    let t2 = t * t;
+    let t3 = t2 * t;
+    0.5 * (2.0 * p1
+        + (p2 - p0) * t
+        + (2.0 * p0 - 5.0 * p1 + 4.0 * p2 - p3) * t2
+        + (3.0 * p1 - p0 - 3.0 * p2 + p3) * t3)
+}
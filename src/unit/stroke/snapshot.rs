@@ -0,0 +1,313 @@
+//! Serializable copies of stroke groups, for the clipboard.
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+use crate::tools::brush::{BrushKind, StrokeStyle};
+
+use super::{PointMeasurement, Stroke, StrokeGroup};
+
+/// Tells clipboard text holding units apart from any other text.
+const FORMAT: &str = "rnote/units";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UnitsSnapshot {
+    format: String,
+    pub groups: Vec<GroupSnapshot>,
+    #[serde(default)]
+    pub unit_groups: Vec<UnitGroupSnapshot>,
+}
+
+impl UnitsSnapshot {
+    pub fn new(groups: Vec<GroupSnapshot>, unit_groups: Vec<UnitGroupSnapshot>) -> Self {
+        Self {
+            format: FORMAT.to_owned(),
+            groups,
+            unit_groups,
+        }
+    }
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("units always serialize")
+    }
+    /// `None` unless the text holds units.
+    pub fn from_json(text: &str) -> Option<Self> {
+        serde_json::from_str::<Self>(text)
+            .ok()
+            .filter(|snapshot| snapshot.format == FORMAT && snapshot.is_valid())
+    }
+    /// Whether every transform can be inverted and every stroke has finite points and a width,
+    /// text from other documents may hold anything.
+    fn is_valid(&self) -> bool {
+        fn valid(groups: &[GroupSnapshot], unit_groups: &[UnitGroupSnapshot]) -> bool {
+            groups.iter().all(|group| {
+                group.transform.is_valid() && group.strokes.iter().all(StrokeSnapshot::is_valid)
+            }) && unit_groups.iter().all(|unit_group| {
+                unit_group.transform.is_valid()
+                    && valid(&unit_group.groups, &unit_group.unit_groups)
+            })
+        }
+        valid(&self.groups, &self.unit_groups)
+    }
+    /// Every stroke group, including the ones inside unit groups.
+    pub fn all_groups(&self) -> Vec<&GroupSnapshot> {
+        fn collect<'a>(
+            groups: &'a [GroupSnapshot],
+            unit_groups: &'a [UnitGroupSnapshot],
+            all: &mut Vec<&'a GroupSnapshot>,
+        ) {
+            all.extend(groups);
+            for unit_group in unit_groups {
+                collect(&unit_group.groups, &unit_group.unit_groups, all);
+            }
+        }
+        let mut all = Vec::new();
+        collect(&self.groups, &self.unit_groups, &mut all);
+        all
+    }
+}
+
+/// World transform of a unit.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransformSnapshot {
+    pub translation: [f32; 3],
+    pub rotation: [f32; 4],
+    pub scale: [f32; 3],
+}
+
+impl TransformSnapshot {
+    fn is_valid(&self) -> bool {
+        let rotation = Quat::from_array(self.rotation);
+        Vec3::from(self.translation).is_finite()
+            && rotation.is_finite()
+            && rotation.length_squared() > f32::EPSILON
+            && Vec3::from(self.scale).is_finite()
+            && !Vec3::from(self.scale).cmpeq(Vec3::ZERO).any()
+    }
+}
+
+impl From<Transform> for TransformSnapshot {
+    fn from(transform: Transform) -> Self {
+        Self {
+            translation: transform.translation.into(),
+            rotation: transform.rotation.into(),
+            scale: transform.scale.into(),
+        }
+    }
+}
+
+impl From<&TransformSnapshot> for Transform {
+    fn from(snapshot: &TransformSnapshot) -> Self {
+        Transform {
+            translation: snapshot.translation.into(),
+            rotation: Quat::from_array(snapshot.rotation).normalize(),
+            scale: snapshot.scale.into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UnitGroupSnapshot {
+    pub transform: TransformSnapshot,
+    pub groups: Vec<GroupSnapshot>,
+    #[serde(default)]
+    pub unit_groups: Vec<UnitGroupSnapshot>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GroupSnapshot {
+    pub transform: TransformSnapshot,
+    pub strokes: Vec<StrokeSnapshot>,
+}
+
+impl GroupSnapshot {
+    pub fn new(stroke_group: &StrokeGroup, world: Transform) -> Self {
+        Self {
+            transform: world.into(),
+            strokes: stroke_group
+                .strokes
+                .iter()
+                .map(StrokeSnapshot::new)
+                .collect(),
+        }
+    }
+    /// Copy of only the strokes at `indices`.
+    pub fn with_strokes(stroke_group: &StrokeGroup, indices: &[usize], world: Transform) -> Self {
+        Self {
+            transform: world.into(),
+            strokes: indices
+                .iter()
+                .filter_map(|&index| stroke_group.strokes.get(index))
+                .map(StrokeSnapshot::new)
+                .collect(),
+        }
+    }
+    pub fn stroke_group(&self) -> StrokeGroup {
+        StrokeGroup {
+            strokes: self.strokes.iter().map(StrokeSnapshot::stroke).collect(),
+            active_stroke: None,
+            started_at: Instant::now(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StrokeSnapshot {
+    pub kind: BrushKind,
+    /// sRGB and alpha.
+    pub color: [f32; 4],
+    pub width: f32,
+    pub opacity: f32,
+    pub measurements: Vec<PointSnapshot>,
+    pub curve: Vec<PointSnapshot>,
+}
+
+impl StrokeSnapshot {
+    fn new(stroke: &Stroke) -> Self {
+        let style = &stroke.style;
+        Self {
+            kind: style.kind,
+            color: style.color.as_rgba_f32(),
+            width: style.width,
+            opacity: style.opacity,
+            measurements: stroke.measurements.iter().map(PointSnapshot::new).collect(),
+            curve: stroke.curve.iter().map(PointSnapshot::new).collect(),
+        }
+    }
+    fn is_valid(&self) -> bool {
+        self.width.is_finite()
+            && self.width > 0.0
+            && self.opacity.is_finite()
+            && self.color.iter().all(|c| c.is_finite())
+            && self
+                .measurements
+                .iter()
+                .chain(&self.curve)
+                .all(PointSnapshot::is_valid)
+    }
+    fn stroke(&self) -> Stroke {
+        let [r, g, b, a] = self.color;
+        Stroke {
+            style: StrokeStyle {
+                kind: self.kind,
+                color: Color::rgba(r, g, b, a),
+                width: self.width,
+                opacity: self.opacity.clamp(0.0, 1.0),
+            },
+            measurements: self
+                .measurements
+                .iter()
+                .map(PointSnapshot::measurement)
+                .collect(),
+            curve: self.curve.iter().map(PointSnapshot::measurement).collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PointSnapshot {
+    pub x: f32,
+    pub y: f32,
+    pub press: Option<f32>,
+    /// Seconds since the group was started.
+    pub time: f32,
+}
+
+impl PointSnapshot {
+    fn new(m: &PointMeasurement) -> Self {
+        Self {
+            x: m.point.x,
+            y: m.point.y,
+            press: m.press,
+            time: m.time.as_secs_f32(),
+        }
+    }
+    fn is_valid(&self) -> bool {
+        self.x.is_finite() && self.y.is_finite() && self.press.is_none_or(f32::is_finite)
+    }
+    fn measurement(&self) -> PointMeasurement {
+        PointMeasurement {
+            point: Vec2::new(self.x, self.y),
+            press: self.press,
+            time: Duration::try_from_secs_f32(self.time).unwrap_or_default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::*;
+
+    /// Clipboard text of one group with a single stroke, after `edit` changed it.
+    fn text(edit: impl FnOnce(&mut Value)) -> String {
+        let mut group = StrokeGroup::new();
+        group.strokes.push(Stroke {
+            measurements: vec![
+                PointMeasurement::new_point(Vec2::ZERO),
+                PointMeasurement::new_point(Vec2::new(10.0, 5.0)),
+            ],
+            ..Stroke::new(StrokeStyle::default())
+        });
+        let snapshot = UnitsSnapshot::new(
+            vec![GroupSnapshot::new(&group, Transform::IDENTITY)],
+            Vec::new(),
+        );
+        let mut value = serde_json::to_value(&snapshot).unwrap();
+        edit(&mut value);
+        value.to_string()
+    }
+
+    fn stroke(value: &mut Value) -> &mut Value {
+        &mut value["groups"][0]["strokes"][0]
+    }
+
+    #[test]
+    fn copied_units_are_pasted() {
+        assert!(UnitsSnapshot::from_json(&text(|_| {})).is_some());
+    }
+
+    #[test]
+    fn strokes_without_finite_points_or_width_are_rejected() {
+        let edits: [fn(&mut Value); 6] = [
+            // too large for an f32, parsed as infinity
+            |value| stroke(value)["width"] = json!(1e39),
+            |value| stroke(value)["width"] = json!(0.0),
+            |value| stroke(value)["width"] = json!(-2.0),
+            |value| stroke(value)["measurements"][1]["x"] = json!(1e39),
+            |value| stroke(value)["measurements"][0]["press"] = json!(-1e39),
+            |value| stroke(value)["curve"] = json!([{ "x": 0.0, "y": 1e39, "time": 0.0 }]),
+        ];
+        for edit in edits {
+            let text = text(edit);
+            assert!(UnitsSnapshot::from_json(&text).is_none(), "{text}");
+        }
+    }
+
+    #[test]
+    fn strokes_inside_unit_groups_are_checked() {
+        let text = text(|value| {
+            let mut group = value["groups"].take();
+            group[0]["strokes"][0]["width"] = json!(1e39);
+            value["unit_groups"] = json!([{
+                "transform": group[0]["transform"].clone(),
+                "groups": group,
+            }]);
+            value["groups"] = json!([]);
+        });
+        assert!(UnitsSnapshot::from_json(&text).is_none(), "{text}");
+    }
+
+    #[test]
+    fn opacity_is_clamped_and_rotation_normalized() {
+        let text = text(|value| {
+            stroke(value)["opacity"] = json!(3.0);
+            value["groups"][0]["transform"]["rotation"] = json!([0.0, 0.0, 0.0, 2.0]);
+        });
+        let snapshot = UnitsSnapshot::from_json(&text).unwrap();
+        let group = &snapshot.groups[0];
+        assert_eq!(group.stroke_group().strokes[0].style.opacity, 1.0);
+        let rotation = Transform::from(&group.transform).rotation;
+        assert!(rotation.abs_diff_eq(Quat::IDENTITY, 1e-6));
+    }
+}
@@ -0,0 +1,404 @@
+//! Turns stroke polylines into triangle meshes with round joins and caps.
+use bevy::{
+    prelude::*,
+    render::{
+        mesh::{Indices, PrimitiveTopology, VertexAttributeValues},
+        render_asset::RenderAssetUsages,
+    },
+};
+use lyon_tessellation::{
+    math::point, path::Path, BuffersBuilder, FillOptions, FillTessellator, FillVertex,
+    VertexBuffers,
+};
+use std::f32::consts::PI;
+
+use crate::tools::brush::StrokeStyle;
+
+use super::{PointMeasurement, PressureSettings};
+
+/// Angle covered by one triangle of a round join or cap.
+const ROUND_STEP: f32 = PI / 8.0;
+/// Consecutive points closer than this are merged, they only produce degenerate segments.
+const MIN_SEGMENT_LENGTH: f32 = 1e-3;
+/// Board units covered by one repetition of an ink texture.
+const TEXTURE_SCALE: f32 = 48.0;
+
+/// How one end of a section of a longer polyline is closed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SectionEnd {
+    /// The polyline ends here, with a cap.
+    Cap,
+    /// The polyline goes on to this point in another section. The section is cut along the
+    /// bisector of the join, so the two sections meet without overlapping.
+    Cut(Vec2),
+}
+
+#[derive(Debug, Default)]
+pub struct StrokeMeshBuilder {
+    positions: Vec<[f32; 3]>,
+    uvs: Vec<[f32; 2]>,
+    indices: Vec<u32>,
+}
+
+impl StrokeMeshBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append a polyline to the mesh, `widths[i]` is the line width at `points[i]`.
+    ///
+    /// Flat lines get square caps and beveled joins instead of round ones. The outline of the
+    /// line is filled once, translucent ink is not blended twice where segments overlap.
+    pub fn add_polyline(&mut self, points: &[Vec2], widths: &[f32], flat: bool) {
+        self.add_section(points, widths, flat, SectionEnd::Cap, SectionEnd::Cap);
+    }
+
+    /// Append a section of a longer polyline, closed by `start` and `end`.
+    pub fn add_section(
+        &mut self,
+        points: &[Vec2],
+        widths: &[f32],
+        flat: bool,
+        start: SectionEnd,
+        end: SectionEnd,
+    ) {
+        let mut path: Vec<(Vec2, f32)> = Vec::with_capacity(points.len());
+        for (&point, &width) in points.iter().zip(widths) {
+            let radius = width * 0.5;
+            match path.last_mut() {
+                Some((last, last_radius)) if last.distance(point) < MIN_SEGMENT_LENGTH => {
+                    *last_radius = last_radius.max(radius);
+                }
+                _ => path.push((point, radius)),
+            }
+        }
+        let outline = match path.as_slice() {
+            [] => return,
+            [(point, radius)] if flat => square(*point, *radius),
+            [(point, radius)] => arc(*point, *radius, Vec2::X, 2.0 * PI).collect(),
+            _ => {
+                // the left side with the end cap, then the same way back
+                let mut outline = side(&path, flat, end);
+                path.reverse();
+                outline.extend(side(&path, flat, start));
+                outline
+            }
+        };
+        self.fill(&outline);
+    }
+
+    /// Append a measured curve drawn with `style`, no thinner than `min_width`.
+    pub fn add_measurements(
+        &mut self,
+        curve: &[PointMeasurement],
+        style: &StrokeStyle,
+        pressure: &PressureSettings,
+        min_width: f32,
+    ) {
+        self.add_measured_section(
+            curve,
+            style,
+            pressure,
+            min_width,
+            SectionEnd::Cap,
+            SectionEnd::Cap,
+        );
+    }
+
+    /// Append a section of a measured curve, closed by `start` and `end`.
+    pub fn add_measured_section(
+        &mut self,
+        curve: &[PointMeasurement],
+        style: &StrokeStyle,
+        pressure: &PressureSettings,
+        min_width: f32,
+        start: SectionEnd,
+        end: SectionEnd,
+    ) {
+        let points: Vec<Vec2> = curve.iter().map(|m| m.point).collect();
+        let widths: Vec<f32> = curve
+            .iter()
+            .map(|m| pressure.stroke_width(style, m.press).max(min_width))
+            .collect();
+        self.add_section(&points, &widths, style.kind.flat_tip(), start, end);
+    }
+
+    fn push_vertex(&mut self, p: Vec2) {
+        self.positions.push([p.x, p.y, 0.0]);
+        self.uvs.push((p / TEXTURE_SCALE).into());
+    }
+
+    /// Triangulate a closed outline, areas it winds around more than once are covered once.
+    fn fill(&mut self, outline: &[Vec2]) {
+        let Some((&first, rest)) = outline.split_first() else {
+            return;
+        };
+        let mut builder = Path::builder();
+        builder.begin(point(first.x, first.y));
+        let mut last = first;
+        for &p in rest {
+            if p.distance(last) >= MIN_SEGMENT_LENGTH {
+                builder.line_to(point(p.x, p.y));
+                last = p;
+            }
+        }
+        builder.end(true);
+        let mut buffers: VertexBuffers<Vec2, u32> = VertexBuffers::new();
+        let result = FillTessellator::new().tessellate_path(
+            &builder.build(),
+            &FillOptions::non_zero(),
+            &mut BuffersBuilder::new(&mut buffers, |vertex: FillVertex| {
+                Vec2::new(vertex.position().x, vertex.position().y)
+            }),
+        );
+        if let Err(err) = result {
+            debug!("stroke outline not filled: {err:?}");
+            return;
+        }
+        let base = self.positions.len() as u32;
+        for vertex in buffers.vertices {
+            self.push_vertex(vertex);
+        }
+        self.indices
+            .extend(buffers.indices.into_iter().map(|index| index + base));
+    }
+
+    /// Append the geometry to a mesh built by [`StrokeMeshBuilder::build`].
+    pub fn append_to(self, mesh: &mut Mesh) {
+        let base = mesh.count_vertices() as u32;
+        if let Some(VertexAttributeValues::Float32x3(positions)) =
+            mesh.attribute_mut(Mesh::ATTRIBUTE_POSITION)
+        {
+            positions.extend(self.positions);
+        }
+        if let Some(VertexAttributeValues::Float32x2(uvs)) =
+            mesh.attribute_mut(Mesh::ATTRIBUTE_UV_0)
+        {
+            uvs.extend(self.uvs);
+        }
+        if let Some(Indices::U32(indices)) = mesh.indices_mut() {
+            indices.extend(self.indices.iter().map(|index| index + base));
+        }
+    }
+
+    pub fn build(self) -> Mesh {
+        Mesh::new(
+            PrimitiveTopology::TriangleList,
+            RenderAssetUsages::default(),
+        )
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, self.positions)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, self.uvs)
+        .with_inserted_indices(Indices::U32(self.indices))
+    }
+}
+
+/// Points of a circular arc around `center`, starting at direction `from` and sweeping `sweep`
+/// radians.
+fn arc(center: Vec2, radius: f32, from: Vec2, sweep: f32) -> impl Iterator<Item = Vec2> {
+    let steps = (sweep.abs() / ROUND_STEP).ceil().max(1.0) as u32;
+    (0..=steps).map(move |step| {
+        let angle = sweep * step as f32 / steps as f32;
+        center + Vec2::from_angle(angle).rotate(from) * radius
+    })
+}
+
+/// Corners of a square of half size `radius` around `center`.
+fn square(center: Vec2, radius: f32) -> Vec<Vec2> {
+    [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)]
+        .map(|(x, y)| center + Vec2::new(x, y) * radius)
+        .to_vec()
+}
+
+/// Outline of the left side of a path of points and radii, followed by the way it is closed at
+/// its end.
+///
+/// Joins are round, or beveled when `flat`, on the outer side of a turn. On the inner side the
+/// outline goes through the point of the join, filling it with the non-zero rule covers every
+/// overlap once.
+fn side(path: &[(Vec2, f32)], flat: bool, end: SectionEnd) -> Vec<Vec2> {
+    let normals: Vec<Vec2> = path
+        .windows(2)
+        .map(|w| (w[1].0 - w[0].0).normalize().perp())
+        .collect();
+    let mut outline = Vec::with_capacity(path.len() * 2);
+    for (i, segment) in path.windows(2).enumerate() {
+        let n = normals[i];
+        let [(p0, r0), (p1, r1)] = [segment[0], segment[1]];
+        outline.push(p0 + n * r0);
+        outline.push(p1 + n * r1);
+        if let Some(&next) = normals.get(i + 1) {
+            let turn = n.angle_between(next);
+            if turn > 0.0 {
+                // the left side is inside the turn
+                outline.push(p1);
+            } else if !flat {
+                outline.extend(arc(p1, r1, n, turn));
+            }
+        }
+    }
+    let (last, radius) = *path.last().unwrap();
+    let n = *normals.last().unwrap();
+    if let SectionEnd::Cut(next) = end {
+        if next.distance(last) >= MIN_SEGMENT_LENGTH {
+            // half of the join, up to its bisector, the section going on covers the other half
+            let turn = n.angle_between((next - last).normalize().perp());
+            let half = turn * 0.5;
+            let bisector = Vec2::from_angle(half).rotate(n);
+            // where the inner sides of both sections cross, kept close for sharp turns
+            let miter = bisector * radius / half.cos().max(0.5);
+            let bevel = bisector * radius * half.cos();
+            if turn > 0.0 {
+                outline.push(last + miter);
+                if flat {
+                    outline.push(last - bevel);
+                } else {
+                    outline.extend(arc(last, radius, -bisector, -half));
+                }
+            } else {
+                if flat {
+                    outline.push(last + bevel);
+                } else {
+                    outline.extend(arc(last, radius, n, half));
+                }
+                outline.push(last - miter);
+            }
+            return outline;
+        }
+    }
+    // end cap, from the left side around the front to the right side
+    if flat {
+        let front = -n.perp() * radius;
+        outline.push(last + n * radius + front);
+        outline.push(last - n * radius + front);
+    } else {
+        outline.extend(arc(last, radius, n, -PI));
+    }
+    outline
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Grid over the meshes of the tests, 4 points per unit.
+    const GRID: (Vec2, usize, usize) = (Vec2::new(-25.0, -25.0), 500, 400);
+
+    /// Most triangles covering any point of a grid over the mesh, and how many points are
+    /// covered at all.
+    fn coverage(builder: &StrokeMeshBuilder) -> (usize, usize) {
+        let (origin, columns, rows) = GRID;
+        let p = |index: u32| Vec2::from_slice(&builder.positions[index as usize][..2]);
+        let mut counts = vec![0; columns * rows];
+        for t in builder.indices.chunks(3) {
+            let [a, b, c] = [p(t[0]), p(t[1]), p(t[2])];
+            let min = ((a.min(b).min(c) - origin) * 4.0).floor().max(Vec2::ZERO);
+            let max = ((a.max(b).max(c) - origin) * 4.0).ceil();
+            for y in min.y as usize..(max.y as usize).min(rows) {
+                for x in min.x as usize..(max.x as usize).min(columns) {
+                    // off the grid lines, so points on shared edges are rare
+                    let q = origin + Vec2::new(x as f32, y as f32) * 0.25 + Vec2::splat(0.01);
+                    let d = [
+                        (b - a).perp_dot(q - a),
+                        (c - b).perp_dot(q - b),
+                        (a - c).perp_dot(q - c),
+                    ];
+                    if d.iter().all(|&d| d > 1e-3) || d.iter().all(|&d| d < -1e-3) {
+                        counts[y * columns + x] += 1;
+                    }
+                }
+            }
+        }
+        let most = counts.iter().copied().max().unwrap_or(0);
+        (most, counts.iter().filter(|&&count| count > 0).count())
+    }
+
+    fn zigzag() -> Vec<Vec2> {
+        (0..12)
+            .map(|i| Vec2::new(i as f32 * 6.0, if i % 2 == 0 { 0.0 } else { 40.0 }))
+            .collect()
+    }
+
+    #[test]
+    fn sharp_turns_are_covered_once() {
+        let points = zigzag();
+        for flat in [false, true] {
+            let mut builder = StrokeMeshBuilder::new();
+            builder.add_polyline(&points, &vec![12.0; points.len()], flat);
+            let (most, covered) = coverage(&builder);
+            assert_eq!(most, 1, "flat {flat}");
+            assert!(covered > 0);
+        }
+    }
+
+    #[test]
+    fn changing_widths_are_covered_once() {
+        let points = zigzag();
+        let widths = (0..points.len())
+            .map(|i| 2.0 + i as f32 * 2.0)
+            .collect::<Vec<_>>();
+        let mut builder = StrokeMeshBuilder::new();
+        builder.add_polyline(&points, &widths, false);
+        assert_eq!(coverage(&builder).0, 1);
+    }
+
+    #[test]
+    fn dense_samples_are_covered_once() {
+        // a tight curve sampled much closer than the line width
+        let points = (0..100)
+            .map(|i| Vec2::new(30.0, 30.0) + Vec2::from_angle(i as f32 * 0.1) * 10.0)
+            .collect::<Vec<_>>();
+        let mut builder = StrokeMeshBuilder::new();
+        builder.add_polyline(&points, &vec![16.0; points.len()], false);
+        assert_eq!(coverage(&builder).0, 1);
+    }
+
+    #[test]
+    fn single_point_is_a_dot() {
+        let mut builder = StrokeMeshBuilder::new();
+        builder.add_polyline(&[Vec2::new(10.0, 10.0)], &[8.0], false);
+        let (most, covered) = coverage(&builder);
+        assert_eq!(most, 1);
+        // about the area of a circle of radius 4, with 16 grid points per unit
+        let area = covered as f32 / 16.0;
+        assert!((area - PI * 16.0).abs() < 4.0, "area {area}");
+    }
+
+    #[test]
+    fn sections_meet_without_gaps_or_overlaps() {
+        // turning left, then right
+        let points = (0..60)
+            .map(|i| {
+                let t = i as f32 * 0.1;
+                Vec2::new(t * 12.0, (t * 0.8).sin() * 30.0 + 20.0)
+            })
+            .collect::<Vec<_>>();
+        let widths = vec![10.0; points.len()];
+        for flat in [false, true] {
+            let mut whole = StrokeMeshBuilder::new();
+            whole.add_polyline(&points, &widths, flat);
+            let mut sections = StrokeMeshBuilder::new();
+            let cuts = [0, 13, 30, 47, points.len() - 1];
+            for w in cuts.windows(2) {
+                let end = |i: usize, next: usize| match i {
+                    0 => SectionEnd::Cap,
+                    i if i == points.len() - 1 => SectionEnd::Cap,
+                    _ => SectionEnd::Cut(points[next]),
+                };
+                sections.add_section(
+                    &points[w[0]..=w[1]],
+                    &widths[w[0]..=w[1]],
+                    flat,
+                    end(w[0], w[0].saturating_sub(1)),
+                    end(w[1], w[1] + 1),
+                );
+            }
+            let (most, covered) = coverage(&sections);
+            assert_eq!(most, 1, "flat {flat}");
+            let whole = coverage(&whole).1 as f32;
+            assert!(
+                (covered as f32 - whole).abs() < whole * 0.002,
+                "flat {flat}"
+            );
+        }
+    }
+}